@@ -0,0 +1,306 @@
+//! High and low heart rate alarms
+
+use std::time::{Duration, Instant, SystemTime};
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AlarmSettings {
+    pub enabled: bool,
+    /// Alarm when the heart rate is above it, unit: bpm
+    pub high: u16,
+    /// Alarm when the heart rate is below it, unit: bpm
+    pub low: u16,
+    /// An alarm ends only after the heart rate is back within the limits by this much, unit: bpm
+    pub hysteresis: u16,
+    /// An alarm starts only after the heart rate is out of the limits for this long, unit: seconds
+    pub min_duration: u16,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AlarmKind {
+    High,
+    Low,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AlarmEvent {
+    pub kind: AlarmKind,
+    pub started: SystemTime,
+    /// `None` if the alarm is still active
+    pub ended: Option<SystemTime>,
+    /// The highest heart rate of a high alarm, or the lowest of a low alarm, unit: bpm
+    pub extreme: u16,
+}
+
+/// Watch the heart rate and raise alarms
+#[derive(Debug, Clone, Default)]
+pub struct AlarmMonitor {
+    /// Out of limits, but not long enough to raise an alarm
+    pending: Option<Pending>,
+    /// Events of this session, the last one is active if it has not ended
+    log: Vec<AlarmEvent>,
+}
+
+/// The heart rate crossed a limit, the alarm is raised if it stays beyond for long enough.
+#[derive(Debug, Clone, Copy)]
+struct Pending {
+    kind: AlarmKind,
+    since: Instant,
+    /// Wall clock of `since`
+    started: SystemTime,
+    extreme: u16,
+}
+
+impl AlarmSettings {
+    /// Set both limits, `high` is kept above `low`.
+    pub fn set_limits(&mut self, high: u16, low: u16) {
+        self.low = low.clamp(30, 120);
+        self.high = high.clamp(self.low + 1, 240);
+    }
+}
+
+impl AlarmMonitor {
+    /// Update with a new heart rate, `None` if it is not reliable (e.g. sensor contact lost).
+    /// Return `true` if an alarm is raised or ended.
+    pub fn update(&mut self, heart_rate: Option<u16>, settings: &AlarmSettings) -> bool {
+        self.update_at(heart_rate, settings, Instant::now(), SystemTime::now())
+    }
+
+    /// [`update`](Self::update) at `now`, and `wall_clock` of it
+    fn update_at(
+        &mut self,
+        heart_rate: Option<u16>,
+        settings: &AlarmSettings,
+        now: Instant,
+        wall_clock: SystemTime,
+    ) -> bool {
+        if !settings.enabled {
+            self.pending = None;
+            return self.end();
+        }
+        let Some(hr) = heart_rate else {
+            self.pending = None;
+            return false;
+        };
+
+        if let Some(event) = self.active_mut() {
+            let back_to_normal = match event.kind {
+                AlarmKind::High => {
+                    event.extreme = event.extreme.max(hr);
+                    hr.saturating_add(settings.hysteresis) <= settings.high
+                }
+                AlarmKind::Low => {
+                    event.extreme = event.extreme.min(hr);
+                    hr >= settings.low.saturating_add(settings.hysteresis)
+                }
+            };
+            return back_to_normal && self.end();
+        }
+
+        let kind = if hr > settings.high {
+            AlarmKind::High
+        } else if hr < settings.low {
+            AlarmKind::Low
+        } else {
+            self.pending = None;
+            return false;
+        };
+        let pending = match &mut self.pending {
+            Some(pending) if pending.kind == kind => {
+                pending.extreme = match kind {
+                    AlarmKind::High => pending.extreme.max(hr),
+                    AlarmKind::Low => pending.extreme.min(hr),
+                };
+                pending
+            }
+            _ => self.pending.insert(Pending {
+                kind,
+                since: now,
+                started: wall_clock,
+                extreme: hr,
+            }),
+        };
+        if now.duration_since(pending.since) < Duration::from_secs(settings.min_duration as u64) {
+            return false;
+        }
+        // The alarm started when the limit was crossed
+        let Pending {
+            started, extreme, ..
+        } = *pending;
+        self.pending = None;
+        self.log.push(AlarmEvent {
+            kind,
+            started,
+            ended: None,
+            extreme,
+        });
+        true
+    }
+
+    pub fn active(&self) -> Option<&AlarmEvent> {
+        self.log.last().filter(|v| v.ended.is_none())
+    }
+
+    pub fn log(&self) -> &[AlarmEvent] {
+        &self.log
+    }
+
+    /// End the active alarm, return `false` if there is none.
+    pub fn end(&mut self) -> bool {
+        match self.active_mut() {
+            Some(event) => {
+                event.ended = Some(SystemTime::now());
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn active_mut(&mut self) -> Option<&mut AlarmEvent> {
+        self.log.last_mut().filter(|v| v.ended.is_none())
+    }
+}
+
+impl Default for AlarmSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            high: 160,
+            low: 45,
+            hysteresis: 5,
+            min_duration: 10,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SETTINGS: AlarmSettings = AlarmSettings {
+        enabled: true,
+        high: 160,
+        low: 45,
+        hysteresis: 5,
+        min_duration: 10,
+    };
+
+    /// Feed the monitor one heart rate per second
+    struct Feed {
+        monitor: AlarmMonitor,
+        start: Instant,
+        wall_clock: SystemTime,
+        secs: u64,
+    }
+
+    impl Feed {
+        fn new() -> Self {
+            Self {
+                monitor: AlarmMonitor::default(),
+                start: Instant::now(),
+                wall_clock: SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000),
+                secs: 0,
+            }
+        }
+
+        fn wall_clock(&self, secs: u64) -> SystemTime {
+            self.wall_clock + Duration::from_secs(secs)
+        }
+
+        /// Return the seconds of changes
+        fn feed(&mut self, heart_rates: &[Option<u16>], settings: &AlarmSettings) -> Vec<u64> {
+            let mut changes = Vec::new();
+            for hr in heart_rates {
+                let now = self.start + Duration::from_secs(self.secs);
+                let wall_clock = self.wall_clock(self.secs);
+                if self.monitor.update_at(*hr, settings, now, wall_clock) {
+                    changes.push(self.secs);
+                }
+                self.secs += 1;
+            }
+            changes
+        }
+    }
+
+    fn repeat(hr: u16, secs: usize) -> Vec<Option<u16>> {
+        vec![Some(hr); secs]
+    }
+
+    #[test]
+    fn raise_after_min_duration() {
+        let mut feed = Feed::new();
+        assert_eq!(feed.feed(&repeat(100, 5), &SETTINGS), Vec::<u64>::new());
+        // Above the limit from 5 s, raised 10 s later
+        let mut heart_rates = repeat(165, 5);
+        heart_rates.extend(repeat(170, 5));
+        heart_rates.extend(repeat(168, 3));
+        assert_eq!(feed.feed(&heart_rates, &SETTINGS), [15]);
+
+        let alarm = feed.monitor.active().unwrap();
+        assert_eq!(alarm.kind, AlarmKind::High);
+        assert_eq!(alarm.started, feed.wall_clock(5));
+        assert_eq!(alarm.extreme, 170);
+    }
+
+    #[test]
+    fn no_alarm_if_back_within_min_duration() {
+        let mut feed = Feed::new();
+        let mut heart_rates = repeat(40, 9);
+        heart_rates.push(Some(50));
+        heart_rates.extend(repeat(40, 9));
+        // Unreliable heart rate restarts the timer too
+        heart_rates.push(None);
+        heart_rates.extend(repeat(40, 9));
+        assert_eq!(feed.feed(&heart_rates, &SETTINGS), Vec::<u64>::new());
+        assert_eq!(feed.monitor.active(), None);
+        assert_eq!(feed.feed(&repeat(40, 2), &SETTINGS), [30]);
+        assert_eq!(feed.monitor.active().unwrap().started, feed.wall_clock(20));
+    }
+
+    #[test]
+    fn no_reraise_within_hysteresis() {
+        let mut feed = Feed::new();
+        assert_eq!(feed.feed(&repeat(170, 11), &SETTINGS), [10]);
+        // Within the limits, but not by the hysteresis
+        let heart_rates = [160, 156, 170, 158, 165, 156].map(Some);
+        assert_eq!(feed.feed(&heart_rates, &SETTINGS), Vec::<u64>::new());
+        assert_eq!(feed.monitor.log().len(), 1);
+        assert_eq!(feed.monitor.active().unwrap().extreme, 170);
+    }
+
+    #[test]
+    fn clear_past_limit_with_hysteresis() {
+        let mut feed = Feed::new();
+        assert_eq!(feed.feed(&repeat(170, 11), &SETTINGS), [10]);
+        assert_eq!(feed.feed(&[Some(155)], &SETTINGS), [11]);
+        assert_eq!(feed.monitor.active(), None);
+        assert!(feed.monitor.log()[0].ended.is_some());
+
+        assert_eq!(feed.feed(&repeat(40, 11), &SETTINGS), [22]);
+        assert_eq!(feed.feed(&[44, 49].map(Some), &SETTINGS), Vec::<u64>::new());
+        assert_eq!(feed.feed(&[Some(50)], &SETTINGS), [25]);
+
+        let kinds = feed
+            .monitor
+            .log()
+            .iter()
+            .map(|v| v.kind)
+            .collect::<Vec<_>>();
+        assert_eq!(kinds, [AlarmKind::High, AlarmKind::Low]);
+        assert_eq!(feed.monitor.log()[1].extreme, 40);
+    }
+
+    #[test]
+    fn disable_ends_alarm() {
+        let mut feed = Feed::new();
+        assert_eq!(feed.feed(&repeat(170, 11), &SETTINGS), [10]);
+        let disabled = AlarmSettings {
+            enabled: false,
+            ..SETTINGS
+        };
+        assert_eq!(feed.feed(&repeat(170, 20), &disabled), [11]);
+        assert_eq!(feed.monitor.active(), None);
+    }
+}
@@ -1,35 +1,51 @@
 use std::sync::Arc;
 
-use btleplug::api::{Central, CentralEvent, CentralState, Manager as _};
-use btleplug::platform::{Adapter, Manager};
-use iced::futures::{Stream, StreamExt, executor};
+use iced::futures::{StreamExt, executor};
 use iced::time::Instant;
 use iced::window::gain_focus;
 use iced::{Task, window};
+use log::warn;
 
-use super::{App, HrsDevice, Message};
+use super::{App, Message};
+use crate::artifact::ArtifactFilter;
 use crate::config::Config;
+use crate::hrs_device::BleSource;
+use crate::hrv::Hrv;
+use crate::replay::Playback;
+use crate::source::{HeartRateSource, SourceEvent, SourceState, UnavailableSource};
 
 impl App {
+    /// Boot with the Bluetooth adapter as heart rate source. Without an adapter, the unknown
+    /// adapter state is shown instead.
     pub fn boot() -> (Self, Task<Message>) {
-        let (adapter, adapter_state, adapter_events) = executor::block_on(init_bluetooth());
-
-        let adapter2 = Arc::new(adapter.clone());
-        let adapter_message = adapter_events.filter_map(move |event| {
-            let adapter2 = adapter2.clone();
-            async move {
-                match event {
-                    CentralEvent::DeviceDiscovered(id) => HrsDevice::from_id(&adapter2, &id)
-                        .await
-                        .map(Message::DiscoveredDevice),
-                    CentralEvent::DeviceDisconnected(_) => Some(Message::DeviceDisconnected),
-                    CentralEvent::StateUpdate(state) => Some(Message::AdapterStateUpdated(state)),
-                    _ => None,
-                }
+        let source: Arc<dyn HeartRateSource> = match executor::block_on(BleSource::new()) {
+            Ok(source) => Arc::new(source),
+            Err(e) => {
+                warn!("Failed to get Bluetooth adapter: {e}");
+                Arc::new(UnavailableSource::default())
             }
+        };
+        Self::boot_with(source, None)
+    }
+
+    /// Boot with the given heart rate source, and the playback controls if it replays a session
+    pub fn boot_with(
+        source: Arc<dyn HeartRateSource>,
+        playback: Option<Arc<Playback>>,
+    ) -> (Self, Task<Message>) {
+        let source_state = executor::block_on(source.state()).unwrap_or_else(|e| {
+            warn!("Failed to get heart rate source state: {e}");
+            SourceState::Unknown
         });
-        let adapter_events = Task::done(Message::AdapterStateUpdated(adapter_state.clone()))
-            .chain(Task::stream(adapter_message));
+        let source_events = executor::block_on(source.events())
+            .expect("Failed to listen heart rate source events.")
+            .map(|event| match event {
+                SourceEvent::DeviceDiscovered(device) => Message::DiscoveredDevice(device),
+                SourceEvent::DeviceDisconnected(_) => Message::DeviceDisconnected,
+                SourceEvent::StateUpdate(state) => Message::SourceStateUpdated(state),
+            });
+        let source_events = Task::done(Message::SourceStateUpdated(source_state))
+            .chain(Task::stream(source_events));
 
         let config = Config::load().unwrap_or_default();
         let (main_window, open_main_window) = create_main_window();
@@ -37,21 +53,44 @@ impl App {
 
         (
             Self {
-                adapter,
-                adapter_state,
+                source,
+                playback,
+                source_state,
                 connection_state: Default::default(),
                 discovered_devices: Vec::new(),
 
                 main_window,
                 hr_window,
+                page: Default::default(),
+                history: Vec::new(),
+                opened_history_entry: None,
+                history_name: String::new(),
+                confirming_delete: false,
+                history_exported: None,
                 selected_device: None,
+                show_device_details: false,
                 heart_rate: None,
+                heart_rate_series: Default::default(),
+                chart_view: Default::default(),
+                last_measurement: None,
+                heart_rate_task: None,
+                resubscribe_attempts: 0,
+                artifact_filter: ArtifactFilter::new(
+                    config.artifact_threshold(),
+                    config.artifact_correction,
+                ),
+                hrv: Hrv::new(config.hrv_window),
+                session_stats: Default::default(),
+                last_summary: None,
+                alarm_monitor: Default::default(),
+                alarm_flash: false,
                 last_error: (String::new(), Instant::now() - iced::time::seconds(5)),
 
-                config,
+                recorder: None,
+                config: config.clone(),
             },
             Task::batch([
-                adapter_events,
+                source_events,
                 open_main_window,
                 open_hr_window,
                 gain_focus(main_window),
@@ -61,34 +100,6 @@ impl App {
     }
 }
 
-async fn init_bluetooth() -> (
-    Adapter,
-    CentralState,
-    impl Stream<Item = CentralEvent> + Send,
-) {
-    let adapter = Manager::new()
-        .await
-        .expect("Failed to get Bluetooth adapter.")
-        .adapters()
-        .await
-        .expect("Failed to get Bluetooth adapter.")
-        .into_iter()
-        .next()
-        .expect("Bluetooth adapter not found.");
-
-    let adapter_state = adapter
-        .adapter_state()
-        .await
-        .expect("Failed to get Bluetooth adapter state.");
-
-    let adapter_events = adapter
-        .events()
-        .await
-        .expect("Failed to listen Bluetooth adapter events.");
-
-    (adapter, adapter_state, adapter_events)
-}
-
 fn create_main_window() -> (window::Id, Task<Message>) {
     let (id, open) = window::open(window::Settings {
         size: (550, 300).into(),
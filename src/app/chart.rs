@@ -0,0 +1,660 @@
+//! Charts drawn on canvas
+
+use std::time::Duration;
+
+use iced::widget::canvas::{self, Action, Frame, Geometry, LineDash, Path, Stroke, Text};
+use iced::widget::text::Alignment;
+use iced::{Color, Point, Rectangle, Renderer, Size, Theme, mouse};
+
+use super::Message;
+use super::view::{format_duration, zone_color};
+use crate::series::{self, HeartRateSeries};
+use crate::zones::{HrZones, Zone};
+
+/// Heart rate trend of the last `window`, without axes. The heart rate range is fitted to the
+/// visible points, and the area below the line is filled with `fill_alpha`.
+pub struct Sparkline<'a> {
+    pub series: &'a HeartRateSeries,
+    pub window: Duration,
+    pub color: Color,
+    pub fill_alpha: f32,
+}
+
+impl Sparkline<'_> {
+    /// Heart rate range is at least this wide, so small fluctuations are not exaggerated
+    const MIN_SPAN: f32 = 10.0;
+}
+
+impl<Message> canvas::Program<Message> for Sparkline<'_> {
+    type State = ();
+
+    fn draw(
+        &self,
+        _state: &Self::State,
+        renderer: &Renderer,
+        _theme: &Theme,
+        bounds: Rectangle,
+        _cursor: mouse::Cursor,
+    ) -> Vec<Geometry> {
+        let mut frame = Frame::new(renderer, bounds.size());
+        let points = self.series.recent(self.window);
+        let (min, max) = points
+            .iter()
+            .filter_map(|v| v.heart_rate)
+            .fold((u16::MAX, u16::MIN), |(min, max), v| {
+                (min.min(v), max.max(v))
+            });
+        if min > max {
+            return vec![frame.into_geometry()];
+        }
+        let center = (min as f32 + max as f32) / 2.0;
+        let span = (max - min) as f32;
+        let span = span.max(Self::MIN_SPAN);
+        let (low, high) = (center - span / 2.0, center + span / 2.0);
+
+        let stroke_width = (bounds.height / 16.0).max(1.0);
+        let now = self.series.elapsed();
+        let window = self.window.as_secs_f32();
+        let to_point = |v: &series::Point, hr: u16| {
+            let age = now.saturating_sub(v.elapsed).as_secs_f32();
+            let ratio = (hr as f32 - low) / (high - low);
+            Point::new(
+                bounds.width * (1.0 - age / window),
+                stroke_width + (bounds.height - stroke_width * 2.0) * (1.0 - ratio),
+            )
+        };
+
+        // Segments are broken where the heart rate is unreliable
+        let segments = points
+            .split(|v| v.heart_rate.is_none())
+            .filter(|v| !v.is_empty());
+        for segment in segments {
+            let line = segment
+                .iter()
+                .filter_map(|v| v.heart_rate.map(|hr| to_point(v, hr)))
+                .collect::<Vec<_>>();
+            let (Some(first), Some(last)) = (line.first(), line.last()) else {
+                continue;
+            };
+            let area = Path::new(|b| {
+                b.move_to(Point::new(first.x, bounds.height));
+                line.iter().for_each(|v| b.line_to(*v));
+                b.line_to(Point::new(last.x, bounds.height));
+                b.close();
+            });
+            frame.fill(
+                &area,
+                Color {
+                    a: self.fill_alpha,
+                    ..self.color
+                },
+            );
+            let path = Path::new(|b| {
+                b.move_to(*first);
+                line.iter().skip(1).for_each(|v| b.line_to(*v));
+            });
+            frame.stroke(
+                &path,
+                Stroke::default()
+                    .with_color(self.color)
+                    .with_width(stroke_width),
+            );
+        }
+        vec![frame.into_geometry()]
+    }
+}
+
+/// Visible time range of [`SessionChart`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ChartView {
+    /// Visible time span, `None` to show the whole session
+    pub span: Option<Duration>,
+    /// End of the visible range since the session started, `None` to follow the latest heart
+    /// rate
+    pub end: Option<Duration>,
+}
+
+/// Heart rate of the session with zone bands as background, and optionally RR-Intervals. Scroll
+/// to zoom, drag to pan, and hover to show the values.
+pub struct SessionChart<'a> {
+    pub series: &'a HeartRateSeries,
+    pub zones: &'a HrZones,
+    pub view: ChartView,
+    pub show_rr: bool,
+}
+
+#[derive(Debug, Default)]
+pub struct ChartState {
+    /// Cursor x and the view when dragging started
+    drag: Option<(f32, ChartView)>,
+}
+
+/// Visible ranges of a chart, unit of time: seconds since the session started
+struct Scale {
+    plot: Rectangle,
+    start: f32,
+    end: f32,
+    /// Heart rate range, unit: bpm
+    low: f32,
+    high: f32,
+}
+
+impl Scale {
+    fn x(&self, secs: f32) -> f32 {
+        self.plot.x + (secs - self.start) / (self.end - self.start) * self.plot.width
+    }
+
+    fn y(&self, heart_rate: f32) -> f32 {
+        self.plot.y + (1.0 - (heart_rate - self.low) / (self.high - self.low)) * self.plot.height
+    }
+
+    fn time_at(&self, x: f32) -> f32 {
+        self.start + (x - self.plot.x) / self.plot.width * (self.end - self.start)
+    }
+}
+
+impl SessionChart<'_> {
+    /// The whole session is shown as at least this long
+    const MIN_FULL_SPAN: Duration = Duration::from_secs(60);
+    const MIN_SPAN: Duration = Duration::from_secs(10);
+    const ZOOM_FACTOR: f32 = 0.8;
+    /// Scrolling more at once is taken as this many lines
+    const MAX_ZOOM_LINES: f32 = 20.0;
+    const MARGIN_LEFT: f32 = 32.0;
+    const MARGIN_RIGHT: f32 = 8.0;
+    /// Right margin with the RR-Interval axis
+    const MARGIN_RIGHT_RR: f32 = 40.0;
+    const MARGIN_TOP: f32 = 8.0;
+    const MARGIN_BOTTOM: f32 = 18.0;
+    const LABEL_SIZE: f32 = 11.0;
+
+    fn plot(&self, bounds: Rectangle) -> Rectangle {
+        let right = if self.show_rr {
+            Self::MARGIN_RIGHT_RR
+        } else {
+            Self::MARGIN_RIGHT
+        };
+        Rectangle {
+            x: Self::MARGIN_LEFT,
+            y: Self::MARGIN_TOP,
+            width: (bounds.width - Self::MARGIN_LEFT - right).max(1.0),
+            height: (bounds.height - Self::MARGIN_TOP - Self::MARGIN_BOTTOM).max(1.0),
+        }
+    }
+
+    /// Visible span and end, limited to the session
+    fn span_and_end(&self, view: ChartView) -> (Duration, Duration) {
+        let now = self.series.elapsed();
+        let full = now.max(Self::MIN_FULL_SPAN);
+        let span = view.span.unwrap_or(full).clamp(Self::MIN_SPAN, full);
+        (span, view.end.unwrap_or(now).max(span).min(now))
+    }
+
+    fn scale(&self, bounds: Rectangle) -> Scale {
+        let (span, end) = self.span_and_end(self.view);
+        let end = end.as_secs_f32();
+        let start = end - span.as_secs_f32();
+        let (min, max) = self
+            .visible_points(start, end)
+            .iter()
+            .filter_map(|v| v.heart_rate)
+            .fold((u16::MAX, u16::MIN), |(min, max), v| {
+                (min.min(v), max.max(v))
+            });
+        let (low, high) = if min > max {
+            (50.0, 150.0)
+        } else {
+            let low = ((min as f32 - 5.0) / 10.0).floor() * 10.0;
+            let high = ((max as f32 + 5.0) / 10.0).ceil() * 10.0;
+            (low, high.max(low + 30.0))
+        };
+        Scale {
+            plot: self.plot(bounds),
+            start,
+            end,
+            low,
+            high,
+        }
+    }
+
+    /// Points between `start` and `end`, with one more point on each side so lines reach the
+    /// edges.
+    fn visible_points(&self, start: f32, end: f32) -> &[series::Point] {
+        let points = self.series.points();
+        let first = points
+            .partition_point(|v| v.elapsed.as_secs_f32() < start)
+            .saturating_sub(1);
+        let last =
+            (points.partition_point(|v| v.elapsed.as_secs_f32() <= end) + 1).min(points.len());
+        &points[first..last.max(first)]
+    }
+
+    /// Zoom `span` by scrolled `lines`, limited to `MIN_SPAN..=full`.
+    fn zoom(span: Duration, lines: f32, full: Duration) -> Duration {
+        // A huge or non-finite scroll must not overflow the span
+        let lines = if lines.is_finite() {
+            lines.clamp(-Self::MAX_ZOOM_LINES, Self::MAX_ZOOM_LINES)
+        } else {
+            0.0
+        };
+        Duration::try_from_secs_f32(span.as_secs_f32() * Self::ZOOM_FACTOR.powf(lines))
+            .map_or(full, |v| v.min(full))
+            .max(Self::MIN_SPAN)
+    }
+
+    /// Publish the new view, it follows the latest heart rate again if `end` reaches now.
+    fn publish(&self, span: Duration, end: Duration) -> Action<Message> {
+        let (span, end) = self.span_and_end(ChartView {
+            span: Some(span),
+            end: Some(end),
+        });
+        let now = self.series.elapsed();
+        let view = ChartView {
+            span: (span < now.max(Self::MIN_FULL_SPAN)).then_some(span),
+            end: (end < now).then_some(end),
+        };
+        Action::publish(Message::ChartViewChanged(view)).and_capture()
+    }
+}
+
+impl canvas::Program<Message> for SessionChart<'_> {
+    type State = ChartState;
+
+    fn update(
+        &self,
+        state: &mut Self::State,
+        event: &canvas::Event,
+        bounds: Rectangle,
+        cursor: mouse::Cursor,
+    ) -> Option<Action<Message>> {
+        let scale = self.scale(bounds);
+        let position = cursor.position_in(bounds);
+        let in_plot = position.filter(|v| scale.plot.contains(*v));
+        let canvas::Event::Mouse(event) = event else {
+            return None;
+        };
+        match event {
+            mouse::Event::WheelScrolled { delta } => {
+                let position = in_plot?;
+                let lines = match delta {
+                    mouse::ScrollDelta::Lines { y, .. } => *y,
+                    mouse::ScrollDelta::Pixels { y, .. } => *y / 50.0,
+                };
+                let (span, _) = self.span_and_end(self.view);
+                let full = self.series.elapsed().max(Self::MIN_FULL_SPAN);
+                let new_span = Self::zoom(span, lines, full);
+                // Keep the time under the cursor in place
+                let ratio = (position.x - scale.plot.x) / scale.plot.width;
+                let time = scale.time_at(position.x);
+                let new_end = time + (1.0 - ratio) * new_span.as_secs_f32();
+                Some(self.publish(new_span, Duration::from_secs_f32(new_end.max(0.0))))
+            }
+            mouse::Event::ButtonPressed(mouse::Button::Left) => {
+                state.drag = Some((in_plot?.x, self.view));
+                Some(Action::capture())
+            }
+            mouse::Event::ButtonReleased(mouse::Button::Left) => {
+                state.drag.take().map(|_| Action::capture())
+            }
+            mouse::Event::CursorMoved { .. } => {
+                let Some((x, view)) = state.drag else {
+                    return position.map(|_| Action::request_redraw());
+                };
+                let x_now = cursor.position_in(bounds).map_or(x, |v| v.x);
+                let (span, end) = self.span_and_end(view);
+                let shift = (x_now - x) / scale.plot.width * span.as_secs_f32();
+                let end = (end.as_secs_f32() - shift).max(0.0);
+                Some(self.publish(span, Duration::from_secs_f32(end)))
+            }
+            _ => None,
+        }
+    }
+
+    fn draw(
+        &self,
+        _state: &Self::State,
+        renderer: &Renderer,
+        theme: &Theme,
+        bounds: Rectangle,
+        cursor: mouse::Cursor,
+    ) -> Vec<Geometry> {
+        let mut frame = Frame::new(renderer, bounds.size());
+        let palette = theme.extended_palette();
+        let text_color = palette.background.base.text;
+        let grid_color = Color {
+            a: 0.2,
+            ..text_color
+        };
+        let scale = self.scale(bounds);
+        let plot = scale.plot;
+        let label = |content: String, position: Point, align_x: Alignment, color: Color| Text {
+            content,
+            position,
+            color,
+            size: Self::LABEL_SIZE.into(),
+            align_x,
+            align_y: iced::alignment::Vertical::Center,
+            ..Default::default()
+        };
+
+        // Zone bands
+        let bounds_bpm = self.zones.bounds();
+        for (i, lower) in bounds_bpm.iter().enumerate() {
+            let upper = bounds_bpm.get(i + 1).map_or(scale.high, |v| *v as f32);
+            let (lower, upper) = ((*lower as f32).max(scale.low), upper.min(scale.high));
+            if lower >= upper {
+                continue;
+            }
+            let (top, bottom) = (scale.y(upper), scale.y(lower));
+            frame.fill_rectangle(
+                Point::new(plot.x, top),
+                Size::new(plot.width, bottom - top),
+                Color {
+                    a: 0.15,
+                    ..zone_color(Zone(i))
+                },
+            );
+        }
+
+        // Heart rate axis
+        let step = if scale.high - scale.low > 100.0 {
+            20
+        } else {
+            10
+        };
+        for bpm in (scale.low as u16..=scale.high as u16).filter(|v| v % step == 0) {
+            let y = scale.y(bpm as f32);
+            frame.stroke(
+                &Path::line(Point::new(plot.x, y), Point::new(plot.x + plot.width, y)),
+                Stroke::default().with_color(grid_color).with_width(1.0),
+            );
+            frame.fill_text(label(
+                bpm.to_string(),
+                Point::new(plot.x - 4.0, y),
+                Alignment::Right,
+                text_color,
+            ));
+        }
+
+        // Time axis
+        let span = scale.end - scale.start;
+        let step = [10, 30, 60, 120, 300, 600, 1200, 1800, 3600]
+            .into_iter()
+            .find(|v| span / *v as f32 <= 6.0)
+            .unwrap_or(7200);
+        let first = (scale.start.max(0.0) / step as f32).ceil() as u64;
+        for secs in (first..)
+            .map(|v| v * step)
+            .take_while(|v| *v as f32 <= scale.end)
+        {
+            let x = scale.x(secs as f32);
+            frame.fill_text(label(
+                format_duration(Duration::from_secs(secs)),
+                Point::new(x, plot.y + plot.height + Self::MARGIN_BOTTOM / 2.0),
+                Alignment::Center,
+                text_color,
+            ));
+        }
+        frame.stroke(
+            &Path::rectangle(plot.position(), plot.size()),
+            Stroke::default().with_color(grid_color).with_width(1.0),
+        );
+
+        let points = self.visible_points(scale.start, scale.end);
+        let in_range =
+            |v: &&series::Point| (scale.start..=scale.end).contains(&v.elapsed.as_secs_f32());
+        let rr_intervals = {
+            let all = self.series.rr_intervals();
+            let first = all.partition_point(|v| v.0.as_secs_f32() < scale.start);
+            let last = all.partition_point(|v| v.0.as_secs_f32() <= scale.end);
+            &all[first..last]
+        };
+        let rr_range = rr_intervals
+            .iter()
+            .fold((f32::MAX, f32::MIN), |(min, max), v| {
+                (min.min(v.1), max.max(v.1))
+            });
+        let rr_range = (rr_range.0 < rr_range.1).then(|| {
+            let padding = ((rr_range.1 - rr_range.0) * 0.1).max(10.0);
+            (rr_range.0 - padding, rr_range.1 + padding)
+        });
+        let rr_y = |rr: f32, (low, high): (f32, f32)| {
+            plot.y + (1.0 - (rr - low) / (high - low)) * plot.height
+        };
+
+        if self.show_rr
+            && let Some(range) = rr_range
+        {
+            let color = Color {
+                a: 0.6,
+                ..palette.secondary.strong.color
+            };
+            for (time, rr) in rr_intervals {
+                let dot = Path::circle(
+                    Point::new(scale.x(time.as_secs_f32()), rr_y(*rr, range)),
+                    1.5,
+                );
+                frame.fill(&dot, color);
+            }
+        }
+
+        // Segments are broken where the heart rate is unreliable
+        let segments = points
+            .split(|v| v.heart_rate.is_none())
+            .filter(|v| v.len() > 1);
+        for segment in segments {
+            let line = clip_line(
+                segment
+                    .iter()
+                    .map(|v| {
+                        let hr = v.heart_rate.unwrap_or_default() as f32;
+                        (v.elapsed.as_secs_f32(), hr)
+                    })
+                    .collect(),
+                scale.start,
+                scale.end,
+            );
+            let path = Path::new(|b| {
+                for (i, (time, hr)) in line.iter().enumerate() {
+                    let point = Point::new(scale.x(*time), scale.y(*hr));
+                    if i == 0 {
+                        b.move_to(point);
+                    } else {
+                        b.line_to(point);
+                    }
+                }
+            });
+            frame.stroke(
+                &path,
+                Stroke::default()
+                    .with_color(palette.primary.strong.color)
+                    .with_width(2.0),
+            );
+        }
+
+        if self.show_rr
+            && let Some(range) = rr_range
+        {
+            for rr in [range.0, range.1] {
+                frame.fill_text(label(
+                    format!("{rr:.0} ms"),
+                    Point::new(plot.x + plot.width + 4.0, rr_y(rr, range)),
+                    Alignment::Left,
+                    palette.secondary.strong.color,
+                ));
+            }
+        }
+
+        // Minimum, maximum and average of the visible heart rate
+        let visible = points
+            .iter()
+            .filter(in_range)
+            .filter_map(|v| v.heart_rate.map(|hr| (v.elapsed, hr)))
+            .collect::<Vec<_>>();
+        if !visible.is_empty() {
+            let average = visible.iter().map(|v| v.1 as f32).sum::<f32>() / visible.len() as f32;
+            let y = scale.y(average);
+            frame.stroke(
+                &Path::line(Point::new(plot.x, y), Point::new(plot.x + plot.width, y)),
+                Stroke {
+                    line_dash: LineDash {
+                        segments: &[4.0, 4.0],
+                        offset: 0,
+                    },
+                    ..Stroke::default().with_color(text_color).with_width(1.0)
+                },
+            );
+            frame.fill_text(label(
+                format!("avg {average:.0}"),
+                Point::new(plot.x + 4.0, y - Self::LABEL_SIZE / 2.0 - 2.0),
+                Alignment::Left,
+                text_color,
+            ));
+            let min = visible.iter().min_by_key(|v| v.1);
+            let max = visible.iter().max_by_key(|v| v.1);
+            for ((time, hr), color, offset) in [
+                (min, palette.success.strong.color, Self::LABEL_SIZE),
+                (max, palette.danger.strong.color, -Self::LABEL_SIZE),
+            ]
+            .into_iter()
+            .filter_map(|(v, color, offset)| v.map(|v| (*v, color, offset)))
+            {
+                let point = Point::new(scale.x(time.as_secs_f32()), scale.y(hr as f32));
+                frame.fill(&Path::circle(point, 3.5), color);
+                frame.fill_text(label(
+                    hr.to_string(),
+                    Point::new(point.x, point.y + offset),
+                    Alignment::Center,
+                    color,
+                ));
+            }
+        }
+
+        // Tooltip of the point under the cursor
+        if let Some(position) = cursor.position_in(bounds).filter(|v| plot.contains(*v)) {
+            let time = scale.time_at(position.x);
+            let nearest = points
+                .iter()
+                .filter(in_range)
+                .filter_map(|v| v.heart_rate.map(|hr| (v.elapsed, hr)))
+                .min_by(|a, b| {
+                    (a.0.as_secs_f32() - time)
+                        .abs()
+                        .total_cmp(&(b.0.as_secs_f32() - time).abs())
+                });
+            if let Some((elapsed, hr)) = nearest {
+                let point = Point::new(scale.x(elapsed.as_secs_f32()), scale.y(hr as f32));
+                frame.stroke(
+                    &Path::line(
+                        Point::new(point.x, plot.y),
+                        Point::new(point.x, plot.y + plot.height),
+                    ),
+                    Stroke::default().with_color(grid_color).with_width(1.0),
+                );
+                frame.fill(&Path::circle(point, 3.0), text_color);
+
+                let mut lines = vec![format_duration(elapsed), format!("{hr} bpm")];
+                if self.show_rr
+                    && let Some((_, rr)) = rr_intervals
+                        .iter()
+                        .filter(|v| v.0.abs_diff(elapsed) <= Duration::from_secs(1))
+                        .min_by_key(|v| v.0.abs_diff(elapsed))
+                {
+                    lines.push(format!("RR {rr:.0} ms"));
+                }
+                let size = Size::new(70.0, lines.len() as f32 * (Self::LABEL_SIZE + 3.0) + 6.0);
+                let x = if point.x + 8.0 + size.width > plot.x + plot.width {
+                    point.x - 8.0 - size.width
+                } else {
+                    point.x + 8.0
+                };
+                let y = (point.y - size.height / 2.0)
+                    .clamp(plot.y, (plot.y + plot.height - size.height).max(plot.y));
+                frame.fill_rectangle(
+                    Point::new(x, y),
+                    size,
+                    Color {
+                        a: 0.9,
+                        ..palette.background.weak.color
+                    },
+                );
+                for (i, line) in lines.into_iter().enumerate() {
+                    frame.fill_text(label(
+                        line,
+                        Point::new(
+                            x + 6.0,
+                            y + 3.0 + (i as f32 + 0.5) * (Self::LABEL_SIZE + 3.0),
+                        ),
+                        Alignment::Left,
+                        palette.background.weak.text,
+                    ));
+                }
+            }
+        }
+
+        vec![frame.into_geometry()]
+    }
+
+    fn mouse_interaction(
+        &self,
+        state: &Self::State,
+        bounds: Rectangle,
+        cursor: mouse::Cursor,
+    ) -> mouse::Interaction {
+        if state.drag.is_some() {
+            mouse::Interaction::Grabbing
+        } else if cursor
+            .position_in(bounds)
+            .is_some_and(|v| self.plot(bounds).contains(v))
+        {
+            mouse::Interaction::Crosshair
+        } else {
+            mouse::Interaction::default()
+        }
+    }
+}
+
+/// Cut the line of `(time, value)` at `start` and `end`, interpolating the value at the cuts. Only
+/// the first and the last point may be outside.
+fn clip_line(mut line: Vec<(f32, f32)>, start: f32, end: f32) -> Vec<(f32, f32)> {
+    let at = |a: (f32, f32), b: (f32, f32), time: f32| {
+        let ratio = (time - a.0) / (b.0 - a.0);
+        (time, a.1 + (b.1 - a.1) * ratio)
+    };
+    if let [first, second, ..] = line[..]
+        && first.0 < start
+        && second.0 > start
+    {
+        line[0] = at(first, second, start);
+    }
+    if let [.., second_last, last] = line[..]
+        && last.0 > end
+        && second_last.0 < end
+    {
+        let n = line.len();
+        line[n - 1] = at(second_last, last, end);
+    }
+    line.retain(|v| (start..=end).contains(&v.0));
+    line
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn zoom_is_limited() {
+        let zoom = SessionChart::zoom;
+        let full = Duration::from_secs(3600);
+        let span = Duration::from_secs(100);
+        assert_eq!(zoom(span, 1.0, full), Duration::from_secs(80));
+        assert_eq!(zoom(span, -1.0, full), Duration::from_secs(125));
+        assert_eq!(zoom(span, 100.0, full), SessionChart::MIN_SPAN);
+        assert_eq!(zoom(span, -100.0, full), full);
+        // Would overflow without limiting the lines
+        assert_eq!(zoom(Duration::MAX, -1e6, full), full);
+        for lines in [f32::NAN, f32::INFINITY, f32::NEG_INFINITY] {
+            assert_eq!(zoom(span, lines, full), span);
+        }
+    }
+}
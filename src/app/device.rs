@@ -0,0 +1,134 @@
+//! Device operation
+//!
+//! Due to the requirements of [iced::Task](iced::Task) on futures, all returned `Future` have
+//! `static` lifetime.
+
+use super::{App, ConnectionState};
+use crate::source::{
+    BatteryLevelStream, BodySensorLocation, Device, DeviceId, DeviceInformation, HeartRateStream,
+    Result,
+};
+
+impl App {
+    /// Connect selected device, return `Ok` that contain the connected device's id if operation
+    /// successful.
+    ///
+    /// # Panic:
+    /// - `selected_device` is `None` or it not in the `discoverd_devices`
+    /// - `connection_state` is not `NotConnected` variant
+    pub(crate) fn connect(&self) -> impl Future<Output = Result<DeviceId>> + 'static {
+        if self.connection_state != ConnectionState::NotConnected {
+            unreachable!(
+                "[BUG] Received a 'ConnectDevice' message, but current connection state is '{:?}'.",
+                self.connection_state
+            );
+        }
+        let id = self
+            .selected_device
+            .clone()
+            .expect("[BUG] Attempt connect a device, but not select any device yet.");
+        let device = self
+            .discovered_devices
+            .iter()
+            .find(|d| d.id() == id)
+            .expect("[BUG] Attempt connect a undiscoverd device.")
+            .clone();
+        async move {
+            device.connect().await?;
+            Ok(id)
+        }
+    }
+
+    /// Disconnect connected device. If no connected device, return Ok.
+    pub(crate) fn disconnect(&self) -> impl Future<Output = Result<()>> + 'static {
+        let device = self.connected_device().cloned();
+        async move {
+            match device {
+                None => Ok(()),
+                Some(device) => device.disconnect().await,
+            }
+        }
+    }
+
+    /// Subscribe the heart rate measurements and return a stream of heart rate for heart rate
+    /// updates. Yield `Err` whe received a invalid heart rate data from device. This stream will
+    /// be closed when the device disconnected.
+    ///
+    /// # Panic
+    /// - no connected device
+    pub(crate) fn subscribe(&self) -> impl Future<Output = Result<HeartRateStream>> + 'static {
+        self.connected_device()
+            .expect("[BUG] Attempt subscribe heart rate, but no device connected.")
+            .subscribe()
+    }
+
+    /// Read the body sensor location of connected device.
+    ///
+    /// # Panic
+    /// - no connected device
+    pub(crate) fn read_body_sensor_location(
+        &self,
+    ) -> impl Future<Output = Result<Option<BodySensorLocation>>> + 'static {
+        self.connected_device()
+            .expect("[BUG] Attempt read body sensor location, but no device connected.")
+            .read_body_sensor_location()
+    }
+
+    /// Read the device information of connected device.
+    ///
+    /// # Panic
+    /// - no connected device
+    pub(crate) fn read_device_information(
+        &self,
+    ) -> impl Future<Output = Result<DeviceInformation>> + 'static {
+        self.connected_device()
+            .expect("[BUG] Attempt read device information, but no device connected.")
+            .read_device_information()
+    }
+
+    /// Read and subscribe the battery level of connected device, return `Ok(None)` if the device
+    /// doesn't provide it.
+    ///
+    /// # Panic
+    /// - no connected device
+    pub(crate) fn subscribe_battery_level(
+        &self,
+    ) -> impl Future<Output = Result<Option<(u8, BatteryLevelStream)>>> + 'static {
+        self.connected_device()
+            .expect("[BUG] Attempt subscribe battery level, but no device connected.")
+            .subscribe_battery_level()
+    }
+
+    /// Reset the _Energy Expended_ of connected device.
+    ///
+    /// # Panic
+    /// - no connected device
+    pub(crate) fn reset_energy_expended(&self) -> impl Future<Output = Result<()>> + 'static {
+        self.connected_device()
+            .expect("[BUG] Attempt reset energy expended, but no device connected.")
+            .reset_energy_expended()
+    }
+
+    /// Start discovering devices
+    pub(crate) fn start_scan(&self) -> impl Future<Output = Result<()>> + 'static {
+        self.source.start_scan()
+    }
+
+    pub(crate) fn stop_scan(&self) -> impl Future<Output = Result<()>> + 'static {
+        self.source.stop_scan()
+    }
+
+    pub(crate) fn connected_device(&self) -> Option<&Device> {
+        let ConnectionState::Connected(id) = &self.connection_state else {
+            return None;
+        };
+        self.discovered_devices.iter().find(|d| d.id() == *id)
+    }
+
+    pub(crate) fn connected_device_mut(&mut self) -> Option<&mut Device> {
+        let ConnectionState::Connected(id) = &self.connection_state else {
+            return None;
+        };
+        self.discovered_devices.iter_mut().find(|d| d.id() == *id)
+    }
+}
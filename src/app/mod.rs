@@ -1,25 +1,49 @@
-mod bluetooth;
 mod boot;
+mod chart;
+mod device;
 mod subscription;
 mod update;
 mod view;
 
-use btleplug::api::{BDAddr, CentralState};
-use btleplug::platform::Adapter;
+use std::path::PathBuf;
+use std::sync::Arc;
+
 use iced::time::Instant;
-use iced::window;
+use iced::{task, window};
+
+use chart::ChartView;
 
+use crate::alarm::AlarmMonitor;
+use crate::artifact::ArtifactFilter;
 use crate::config::Config;
-use crate::hrm::HeartRateMeasurement;
-use crate::hrs_device::HrsDevice;
+use crate::export::Format;
+use crate::history::HistoryEntry;
+use crate::hrm::{HeartRateMeasurement, HrmParseError};
+use crate::hrv::{Hrv, HrvWindow};
 use crate::locales::Language;
+use crate::recorder::Recorder;
+use crate::replay::{Playback, Speed};
+use crate::series::HeartRateSeries;
+use crate::source::{
+    BodySensorLocation, Device, DeviceId, DeviceInformation, HeartRateSource, SourceState,
+};
+use crate::summary::{SessionStats, SessionSummary};
+use crate::zones::{Zone, ZoneMethod};
 
 #[derive(Clone, Debug, PartialEq, Eq, Default)]
 pub enum ConnectionState {
     #[default]
     NotConnected,
     Connecting,
-    Connected(BDAddr),
+    Connected(DeviceId),
+}
+
+/// Page of the main window
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Page {
+    #[default]
+    Monitor,
+    History,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -31,39 +55,129 @@ pub enum BlockResize {
 #[derive(Debug, Clone)]
 pub enum Message {
     Exit,
-    SelectDevice(BDAddr),
+    SelectDevice(DeviceId),
     ConnectDevice,
     DisconnectDevice,
+    ResetEnergyExpended,
+    TogglePlayback,
+    /// Unit: seconds
+    PlaybackSeek(f32),
+    PlaybackSpeedChanged(Speed),
     ShowHeartRateWindow(bool),
     LockHeartRateWindow(bool),
     MouseEvent(iced::mouse::Event, window::Id),
     LanguageChanged(Language),
     HeartRateWindowOpaqueChanged(f32),
+    ShowHrvInHeartRateWindow(bool),
+    ShowBatteryInHeartRateWindow(bool),
+    ShowTrendInHeartRateWindow(bool),
+    /// Unit: seconds
+    TrendWindowChanged(u16),
+    ChartViewChanged(ChartView),
+    ShowRrInChart(bool),
+    BatteryWarningLevelChanged(u8),
+    HrvWindowChanged(HrvWindow),
+    ArtifactThresholdChanged(f32),
+    ArtifactCorrectionToggled(bool),
+    ZoneMethodChanged(ZoneMethod),
+    MaxHeartRateChanged(u16),
+    RestingHeartRateChanged(u16),
+    /// Lower bound of the zone, in percent or bpm depending on the zone method
+    ZoneBoundChanged(Zone, u16),
+    ZoneColorsToggled(bool),
+    HideHeartRateWithoutContactToggled(bool),
+    /// Unit: seconds
+    StaleTimeoutChanged(u8),
+    StaleResubscribeToggled(bool),
+    AlarmsToggled(bool),
+    AlarmHighChanged(u16),
+    AlarmLowChanged(u16),
+    AlarmHysteresisChanged(u16),
+    /// Unit: seconds
+    AlarmMinDurationChanged(u16),
+    /// Toggle the flashing state of the heart rate window while an alarm is active
+    AlarmFlash,
+    RecordSessionsToggled(bool),
+    RecordDirectoryChanged(String),
+    ShowPage(Page),
+    HistoryLoaded(Vec<HistoryEntry>),
+    /// Show details of the session
+    OpenHistoryEntry(PathBuf),
+    HistoryNameEdited(String),
+    RenameHistoryEntry,
+    HistoryEntryRenamed(HistoryEntry),
+    /// Delete the opened session, require to be sent twice to confirm. The session being recorded
+    /// can't be deleted.
+    DeleteHistoryEntry,
+    /// Path of the deleted session
+    HistoryEntryDeleted(PathBuf),
+    ExportHistoryEntry(Format),
+    HistoryEntryExported(PathBuf),
 
     /// In certain situations (such as system hibernation), Bluetooth events may not be received
     /// correctly, requiring periodic checks.
     CheckState,
+    /// Check if the heart rate is stale, and resubscribe it if enabled
+    CheckStale,
     HeartRateWindowResize(BlockResize),
     ScanDevice(bool),
-    AdapterStateUpdated(CentralState),
+    SourceStateUpdated(SourceState),
     ConnectionStateUpdated(ConnectionState),
-    DiscoveredDevice(HrsDevice),
+    DiscoveredDevice(Device),
+    /// Read the optional characteristics of connected device, after its services discovered.
+    ReadDeviceDetails,
+    BodySensorLocationUpdated(Option<BodySensorLocation>),
+    BatteryLevelUpdated(u8),
+    DeviceInformationUpdated(Option<DeviceInformation>),
+    ToggleDeviceDetails,
     DeviceDisconnected,
     HeartRateUpdated(HeartRateMeasurement),
+    InvalidHeartRateData(HrmParseError),
     ErrorOccurred(String),
 }
 
 #[derive(Debug)]
 pub struct App {
-    adapter: Adapter,
-    adapter_state: CentralState,
+    source: Arc<dyn HeartRateSource>,
+    /// Playback controls, only available if the source replays a recorded session
+    playback: Option<Arc<Playback>>,
+    source_state: SourceState,
     connection_state: ConnectionState,
-    discovered_devices: Vec<HrsDevice>,
+    discovered_devices: Vec<Device>,
 
     main_window: window::Id,
     hr_window: window::Id,
-    selected_device: Option<BDAddr>,
+    page: Page,
+    history: Vec<HistoryEntry>,
+    /// Path of the session showing details
+    opened_history_entry: Option<PathBuf>,
+    /// Name of the opened session being edited
+    history_name: String,
+    confirming_delete: bool,
+    /// Path of the last exported file of the opened session
+    history_exported: Option<PathBuf>,
+    selected_device: Option<DeviceId>,
+    show_device_details: bool,
     heart_rate: Option<HeartRateMeasurement>,
+    /// Heart rate of the current or last connection
+    heart_rate_series: HeartRateSeries,
+    chart_view: ChartView,
+    /// When the last measurement is received, or the heart rate is subscribed
+    last_measurement: Option<Instant>,
+    /// Subscription of the heart rate, aborted when resubscribing
+    heart_rate_task: Option<task::Handle>,
+    /// Resubscribe attempts since the heart rate went stale
+    resubscribe_attempts: u8,
+    artifact_filter: ArtifactFilter,
+    hrv: Hrv,
+    session_stats: SessionStats,
+    /// Summary of the last connection, kept until the next connection
+    last_summary: Option<SessionSummary>,
+    /// Alarms of the current or last connection
+    alarm_monitor: AlarmMonitor,
+    alarm_flash: bool,
+    /// Recording the current connection, if enabled
+    recorder: Option<Recorder>,
     last_error: (String, Instant),
 
     config: Config,
@@ -80,7 +194,26 @@ impl App {
         (self.last_error.1.elapsed() < iced::time::seconds(5)).then_some(&self.last_error.0)
     }
 
+    /// Playback controls of the connected replay device
+    fn playback(&self) -> Option<&Playback> {
+        self.connected_device().and(self.playback.as_deref())
+    }
+
     fn set_error_message(&mut self, msg: String) {
         self.last_error = (msg, Instant::now())
     }
+
+    /// The device is connected, but no measurement is received within the timeout. A replay is
+    /// never stale, it stops on pause or at the end, and resubscribing restarts it.
+    fn is_stale(&self) -> bool {
+        self.playback.is_none()
+            && self.last_measurement.is_some_and(|v| {
+                v.elapsed() >= iced::time::seconds(self.config.stale_timeout() as u64)
+            })
+    }
+
+    fn opened_history_entry(&self) -> Option<&HistoryEntry> {
+        let path = self.opened_history_entry.as_ref()?;
+        self.history.iter().find(|v| &v.path == path)
+    }
 }
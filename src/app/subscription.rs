@@ -6,7 +6,19 @@ use super::{App, Message};
 impl App {
     pub fn subscription(&self) -> Subscription<Message> {
         use iced::mouse;
+        let alarm_flash = match self.alarm_monitor.active() {
+            Some(_) => iced::time::every(iced::time::Duration::from_millis(500))
+                .map(|_| Message::AlarmFlash),
+            None => Subscription::none(),
+        };
+        // Also refreshes the stale state of the heart rate window
+        let check_stale = match self.connected_device() {
+            Some(_) => iced::time::every(iced::time::seconds(1)).map(|_| Message::CheckStale),
+            None => Subscription::none(),
+        };
         Subscription::batch([
+            alarm_flash,
+            check_stale,
             iced::time::every(iced::time::Duration::from_mins(1)).map(|_| Message::CheckState),
             window::close_events().map(|_| Message::Exit),
             iced::event::listen_with(|event, status, id| {
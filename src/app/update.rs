@@ -1,10 +1,17 @@
-use btleplug::api::{Central, CentralState};
+use iced::time::Instant;
 use iced::{Task, window};
-use log::{debug, warn};
+use log::{debug, info, warn};
 
-use super::{App, BlockResize, ConnectionState, Message};
+use super::{App, BlockResize, ConnectionState, Message, Page};
+use crate::export;
+use crate::history;
+use crate::recorder::Recorder;
+use crate::source::SourceState;
 use Message::*;
 
+/// Disconnect if the heart rate is still stale after this many resubscribe attempts
+const MAX_RESUBSCRIBE_ATTEMPTS: u8 = 3;
+
 impl App {
     pub fn update(&mut self, message: Message) -> Task<Message> {
         debug!("Received message: {message:?}");
@@ -42,7 +49,7 @@ impl App {
                 window::resize(id, self.config.hr_window_size())
             }
             Exit => {
-                let mut config = self.config;
+                let mut config = self.config.clone();
                 Task::batch([
                     window::position(self.hr_window).then(move |opt| {
                         if let Some(p) = opt {
@@ -85,24 +92,278 @@ impl App {
                 .and_then(|e| {
                     Task::done(ErrorOccurred(format!("Failed to disconnect device: {e}")))
                 }),
+            ResetEnergyExpended => Task::future(self.reset_energy_expended())
+                .map(|res| res.err())
+                .and_then(|e| {
+                    Task::done(ErrorOccurred(format!(
+                        "Failed to reset energy expended: {e}"
+                    )))
+                }),
+            TogglePlayback => {
+                if let Some(playback) = self.playback() {
+                    playback.set_paused(!playback.is_paused());
+                }
+                Task::none()
+            }
+            PlaybackSeek(secs) => {
+                if let Some(playback) = self.playback() {
+                    playback.seek(iced::time::Duration::from_secs_f32(secs));
+                }
+                Task::none()
+            }
+            PlaybackSpeedChanged(speed) => {
+                if let Some(playback) = self.playback() {
+                    playback.set_speed(speed);
+                }
+                Task::none()
+            }
             HeartRateWindowOpaqueChanged(opaque) => {
                 self.config.hr_window_opaque = opaque;
                 Task::none()
             }
+            ShowHrvInHeartRateWindow(show) => {
+                self.config.hr_window_show_hrv = show;
+                window::resize(self.hr_window, self.config.hr_window_size())
+            }
+            ShowBatteryInHeartRateWindow(show) => {
+                self.config.hr_window_show_battery = show;
+                window::resize(self.hr_window, self.config.hr_window_size())
+            }
+            ShowTrendInHeartRateWindow(show) => {
+                self.config.hr_window_show_trend = show;
+                window::resize(self.hr_window, self.config.hr_window_size())
+            }
+            TrendWindowChanged(secs) => {
+                self.config.set_hr_window_trend_secs(secs);
+                Task::none()
+            }
+            ChartViewChanged(view) => {
+                self.chart_view = view;
+                Task::none()
+            }
+            ShowRrInChart(show) => {
+                self.config.chart_show_rr = show;
+                Task::none()
+            }
+            BatteryWarningLevelChanged(level) => {
+                self.config.battery_warning_level = level;
+                Task::none()
+            }
+            HrvWindowChanged(window) => {
+                self.config.hrv_window = window;
+                self.hrv.set_window(window);
+                Task::none()
+            }
+            ArtifactThresholdChanged(threshold) => {
+                let threshold = self.config.set_artifact_threshold(threshold);
+                self.artifact_filter.set_threshold(threshold);
+                Task::none()
+            }
+            ArtifactCorrectionToggled(enable) => {
+                self.config.artifact_correction = enable;
+                self.artifact_filter.set_correction(enable);
+                Task::none()
+            }
+            ZoneMethodChanged(method) => {
+                self.config.hr_zones.method = method;
+                Task::none()
+            }
+            MaxHeartRateChanged(value) => {
+                self.config.hr_zones.set_max_heart_rate(value);
+                Task::none()
+            }
+            RestingHeartRateChanged(value) => {
+                self.config.hr_zones.set_resting_heart_rate(value);
+                Task::none()
+            }
+            ZoneBoundChanged(zone, value) => {
+                self.config.hr_zones.set_raw_bound(zone, value);
+                Task::none()
+            }
+            ZoneColorsToggled(enable) => {
+                self.config.hr_window_zone_colors = enable;
+                Task::none()
+            }
+            HideHeartRateWithoutContactToggled(enable) => {
+                self.config.hide_heart_rate_without_contact = enable;
+                Task::none()
+            }
+            StaleTimeoutChanged(secs) => {
+                self.config.set_stale_timeout(secs);
+                Task::none()
+            }
+            StaleResubscribeToggled(enable) => {
+                self.config.stale_resubscribe = enable;
+                Task::none()
+            }
+            AlarmsToggled(enable) => {
+                self.config.alarm.enabled = enable;
+                self.update_alarm()
+            }
+            AlarmHighChanged(value) => {
+                let low = self.config.alarm.low;
+                self.config.alarm.set_limits(value, low);
+                Task::none()
+            }
+            AlarmLowChanged(value) => {
+                let high = self.config.alarm.high;
+                self.config.alarm.set_limits(high, value);
+                Task::none()
+            }
+            AlarmHysteresisChanged(value) => {
+                self.config.alarm.hysteresis = value;
+                Task::none()
+            }
+            AlarmMinDurationChanged(value) => {
+                self.config.alarm.min_duration = value;
+                Task::none()
+            }
+            AlarmFlash => {
+                self.alarm_flash = !self.alarm_flash;
+                Task::none()
+            }
+            RecordSessionsToggled(enable) => {
+                self.config.record_sessions = enable;
+                if enable {
+                    self.start_recording()
+                } else {
+                    self.recorder = None;
+                    Task::none()
+                }
+            }
+            RecordDirectoryChanged(dir) => {
+                self.config.record_directory = dir.into();
+                Task::none()
+            }
+            ShowPage(page) => {
+                self.page = page;
+                if page != Page::History {
+                    return Task::none();
+                }
+                let dir = self.config.record_directory.clone();
+                Task::future(blocking(move || history::load(&dir))).map(|res| match res {
+                    Ok(history) => HistoryLoaded(history),
+                    Err(e) => ErrorOccurred(format!("Failed to load history: {e}")),
+                })
+            }
+            HistoryLoaded(history) => {
+                self.history = history;
+                if self.opened_history_entry().is_none() {
+                    self.opened_history_entry = None;
+                }
+                Task::none()
+            }
+            OpenHistoryEntry(path) => {
+                self.opened_history_entry = Some(path);
+                self.history_name = self
+                    .opened_history_entry()
+                    .map(|v| v.meta.name.clone())
+                    .unwrap_or_default();
+                self.confirming_delete = false;
+                self.history_exported = None;
+                Task::none()
+            }
+            HistoryEntryExported(path) => {
+                info!("Exported session to {}", path.display());
+                self.history_exported = Some(path);
+                Task::none()
+            }
+            HistoryNameEdited(name) => {
+                self.history_name = name;
+                Task::none()
+            }
+            RenameHistoryEntry => {
+                let name = self.history_name.trim().to_string();
+                let Some(mut entry) = self.opened_history_entry().cloned() else {
+                    return Task::none();
+                };
+                Task::future(blocking(move || entry.rename(name).map(|()| entry))).map(|res| {
+                    match res {
+                        Ok(entry) => HistoryEntryRenamed(entry),
+                        Err(e) => ErrorOccurred(format!("Failed to rename session: {e}")),
+                    }
+                })
+            }
+            HistoryEntryRenamed(entry) => {
+                if let Some(v) = self.history.iter_mut().find(|v| v.path == entry.path) {
+                    *v = entry;
+                }
+                Task::none()
+            }
+            DeleteHistoryEntry => {
+                if !self.confirming_delete {
+                    self.confirming_delete = true;
+                    return Task::none();
+                }
+                self.confirming_delete = false;
+                let Some(entry) = self.opened_history_entry().cloned() else {
+                    return Task::none();
+                };
+                if self
+                    .recorder
+                    .as_ref()
+                    .is_some_and(|v| v.path() == entry.path)
+                {
+                    return Task::done(ErrorOccurred(
+                        "Can't delete the session being recorded".into(),
+                    ));
+                }
+                let path = entry.path.clone();
+                Task::future(blocking(move || entry.delete().map(|()| path))).map(|res| match res {
+                    Ok(path) => HistoryEntryDeleted(path),
+                    Err(e) => ErrorOccurred(format!("Failed to delete session: {e}")),
+                })
+            }
+            HistoryEntryDeleted(path) => {
+                self.history.retain(|v| v.path != path);
+                if self.opened_history_entry.as_ref() == Some(&path) {
+                    self.opened_history_entry = None;
+                }
+                Task::none()
+            }
+            ExportHistoryEntry(format) => {
+                let Some(path) = self.opened_history_entry.clone() else {
+                    return Task::none();
+                };
+                Task::future(blocking(move || export::export_file(&path, format))).map(move |res| {
+                    match res {
+                        Ok(output) => HistoryEntryExported(output),
+                        Err(e) => ErrorOccurred(format!("Failed to export to {format}: {e}")),
+                    }
+                })
+            }
+            CheckStale => {
+                if !self.config.stale_resubscribe || !self.is_stale() {
+                    return Task::none();
+                }
+                if self.resubscribe_attempts >= MAX_RESUBSCRIBE_ATTEMPTS {
+                    warn!("No heart rate received after resubscribing, disconnecting");
+                    self.resubscribe_attempts = 0;
+                    return Task::done(ErrorOccurred(
+                        "No heart rate received, disconnected".into(),
+                    ))
+                    .chain(Task::done(DisconnectDevice))
+                    .chain(Task::done(DeviceDisconnected));
+                }
+                self.resubscribe_attempts += 1;
+                info!(
+                    "Heart rate is stale, resubscribing (attempt {}/{MAX_RESUBSCRIBE_ATTEMPTS})",
+                    self.resubscribe_attempts
+                );
+                self.subscribe_heart_rate(false)
+            }
             CheckState => {
-                let adapter = self.adapter.clone();
-                let adapter_state = self.adapter_state.clone();
+                let source_state = self.source_state;
                 let connected_device = self.connected_device();
-                let check_adapter_state =
-                    Task::future(async move { adapter.adapter_state().await.ok() }).and_then(
-                        move |state| {
-                            if state != adapter_state {
-                                Task::done(AdapterStateUpdated(state))
-                            } else {
-                                Task::none()
-                            }
-                        },
-                    );
+                let check_source_state = Task::future(self.source.state())
+                    .map(|res| res.ok())
+                    .and_then(move |state| {
+                        if state != source_state {
+                            Task::done(SourceStateUpdated(state))
+                        } else {
+                            Task::none()
+                        }
+                    });
                 let check_connect = match connected_device {
                     None => Task::none(),
                     Some(device) => Task::future(device.is_connected())
@@ -115,12 +376,12 @@ impl App {
                             }
                         }),
                 };
-                check_adapter_state.chain(check_connect)
+                check_source_state.chain(check_connect)
             }
-            AdapterStateUpdated(state) => {
-                self.adapter_state = state;
-                match (&self.adapter_state, &self.connected_device()) {
-                    (CentralState::PoweredOn, _) => Task::done(ScanDevice(true)),
+            SourceStateUpdated(state) => {
+                self.source_state = state;
+                match (&self.source_state, &self.connected_device()) {
+                    (SourceState::PoweredOn, _) => Task::done(ScanDevice(true)),
                     (_, Some(_)) => Task::done(DeviceDisconnected),
                     _ => Task::none(),
                 }
@@ -130,25 +391,14 @@ impl App {
                 let ConnectionState::Connected(_) = self.connection_state else {
                     return Task::none();
                 };
+                self.last_summary = None;
+                self.session_stats = Default::default();
+                self.alarm_monitor = Default::default();
+                self.heart_rate_series = Default::default();
+                self.chart_view = Default::default();
 
-                Task::future(self.subscribe()).then(|res| match res {
-                    Err(e) => {
-                        Task::done(ErrorOccurred(format!("Failed to get heart rate data: {e}")))
-                            .chain(Task::done(DisconnectDevice))
-                    }
-                    Ok(s) => Task::done(ScanDevice(false))
-                        .chain(Task::run(s, |opt| match opt {
-                            None => {
-                                warn!("Received invalid heart rate data");
-                                ErrorOccurred("Invalid heart rate data".into())
-                            }
-                            Some(hrm) => HeartRateUpdated(hrm),
-                        }))
-                        // In some case, the device disconnected, but there is still some data in
-                        // the stream, causing `heart_rate` to not be `None`. Therefor, the
-                        // `DeviceDisconnected` message is sent again after the stream ends.
-                        .chain(Task::done(Message::DeviceDisconnected)),
-                })
+                let start_recording = self.start_recording();
+                start_recording.chain(self.subscribe_heart_rate(true))
             }
             ScanDevice(start) => {
                 if start {
@@ -169,8 +419,26 @@ impl App {
             }
             DeviceDisconnected => {
                 self.connection_state = ConnectionState::NotConnected;
+                if let Some(summary) = self.session_stats.summary() {
+                    self.last_summary = Some(summary);
+                }
+                self.session_stats = Default::default();
                 self.heart_rate = None;
-                if CentralState::PoweredOn == self.adapter_state {
+                self.last_measurement = None;
+                self.heart_rate_task = None;
+                self.resubscribe_attempts = 0;
+                if self.alarm_monitor.end()
+                    && let Some(Err(e)) = self
+                        .recorder
+                        .as_mut()
+                        .map(|r| r.save_alarms(self.alarm_monitor.log()))
+                {
+                    warn!("Failed to save alarms: {e}");
+                }
+                self.recorder = None;
+                self.artifact_filter.clear();
+                self.hrv.clear();
+                if SourceState::PoweredOn == self.source_state {
                     Task::done(ScanDevice(true))
                 } else {
                     Task::none()
@@ -182,6 +450,67 @@ impl App {
                 let mode = if show { Windowed } else { Hidden };
                 window::set_mode(self.hr_window, mode).chain(window::gain_focus(self.main_window))
             }
+            ReadDeviceDetails => {
+                if self.connected_device().is_none() {
+                    return Task::none();
+                }
+                let location =
+                    Task::future(self.read_body_sensor_location()).map(|res| match res {
+                        Ok(location) => BodySensorLocationUpdated(location),
+                        Err(e) => {
+                            warn!("Failed to read body sensor location: {e}");
+                            BodySensorLocationUpdated(None)
+                        }
+                    });
+                let battery = Task::future(self.subscribe_battery_level()).then(|res| match res {
+                    Ok(Some((level, s))) => Task::done(BatteryLevelUpdated(level))
+                        .chain(Task::run(s, BatteryLevelUpdated)),
+                    Ok(None) => Task::none(),
+                    Err(e) => {
+                        warn!("Failed to read battery level: {e}");
+                        Task::none()
+                    }
+                });
+                let information =
+                    Task::future(self.read_device_information()).map(|res| match res {
+                        Ok(info) => DeviceInformationUpdated(Some(info)),
+                        Err(e) => {
+                            warn!("Failed to read device information: {e}");
+                            DeviceInformationUpdated(None)
+                        }
+                    });
+                Task::batch([location, battery, information])
+            }
+            BodySensorLocationUpdated(location) => {
+                if let Some(device) = self.connected_device_mut() {
+                    device.set_body_sensor_location(location);
+                }
+                Task::none()
+            }
+            BatteryLevelUpdated(level) => {
+                let warning_level = self.config.battery_warning_level;
+                let Some(device) = self.connected_device_mut() else {
+                    return Task::none();
+                };
+                let previous = device.battery_level();
+                device.set_battery_level(Some(level));
+                if level < warning_level && previous.is_none_or(|v| v >= warning_level) {
+                    warn!("Battery level is low: {level} %");
+                    Task::done(ErrorOccurred(format!("Battery level is low: {level} %")))
+                } else {
+                    Task::none()
+                }
+            }
+            DeviceInformationUpdated(info) => {
+                if let Some(device) = self.connected_device_mut() {
+                    device.set_device_information(info);
+                }
+                Task::none()
+            }
+            ToggleDeviceDetails => {
+                self.show_device_details = !self.show_device_details;
+                Task::none()
+            }
             DiscoveredDevice(device) => {
                 self.discovered_devices.push(device);
                 Task::none()
@@ -191,8 +520,39 @@ impl App {
                 Task::none()
             }
             HeartRateUpdated(rate) => {
+                let mut rr_intervals = Vec::new();
+                for rr in rate.rr_intervals_ms() {
+                    let processed = self.artifact_filter.process(rr);
+                    if processed.is_empty() {
+                        self.hrv.push_gap();
+                        self.session_stats.push_rr_gap();
+                    }
+                    for rr in &processed {
+                        self.hrv.push(*rr);
+                        self.session_stats.push_rr(*rr);
+                    }
+                    rr_intervals.extend(processed);
+                }
+                self.session_stats.push(&rate, &self.config.hr_zones);
+                self.last_measurement = Some(Instant::now());
+                self.resubscribe_attempts = 0;
+                self.heart_rate_series.push(
+                    (rate.heart_rate > 0 && !rate.contact_lost()).then_some(rate.heart_rate),
+                    rr_intervals,
+                );
+                let recorded = self.recorder.as_mut().map(|r| r.record(&rate));
                 self.heart_rate = Some(rate);
-                Task::none()
+                if let Some(Err(e)) = recorded {
+                    warn!("Failed to record heart rate: {e}");
+                    self.recorder = None;
+                    return Task::done(ErrorOccurred(format!("Failed to record heart rate: {e}")));
+                }
+                self.update_alarm()
+            }
+            InvalidHeartRateData(e) => {
+                warn!("Received invalid heart rate data: {e}");
+                self.session_stats.push_invalid();
+                Task::done(ErrorOccurred(format!("Invalid heart rate data: {e}")))
             }
             ErrorOccurred(msg) => {
                 self.set_error_message(msg);
@@ -203,4 +563,100 @@ impl App {
             }
         }
     }
+
+    /// Subscribe the heart rate of connected device, replacing the previous subscription. The
+    /// device details are read after subscribed if `read_details`.
+    fn subscribe_heart_rate(&mut self, read_details: bool) -> Task<Message> {
+        let (subscribe, handle) = Task::future(self.subscribe())
+            .then(move |res| match res {
+                Err(e) => {
+                    warn!("Failed to get heart rate data: {e}");
+                    Task::done(ErrorOccurred(format!("Failed to get heart rate data: {e}")))
+                        .chain(Task::done(DisconnectDevice))
+                        // Don't wait the source event, it may never arrive if the device is
+                        // already gone.
+                        .chain(Task::done(DeviceDisconnected))
+                }
+                Ok(s) => {
+                    let details = if read_details {
+                        Task::done(ScanDevice(false)).chain(Task::done(ReadDeviceDetails))
+                    } else {
+                        Task::none()
+                    };
+                    details
+                        .chain(Task::run(s, |res| match res {
+                            Err(e) => InvalidHeartRateData(e),
+                            Ok(hrm) => HeartRateUpdated(hrm),
+                        }))
+                        // In some case, the device disconnected, but there is still some data in
+                        // the stream, causing `heart_rate` to not be `None`. Therefor, the
+                        // `DeviceDisconnected` message is sent again after the stream ends.
+                        .chain(Task::done(Message::DeviceDisconnected))
+                }
+            })
+            .abortable();
+        if let Some(previous) = self.heart_rate_task.replace(handle) {
+            previous.abort();
+        }
+        // Wait a full timeout for the first measurement
+        self.last_measurement = Some(Instant::now());
+        subscribe
+    }
+
+    /// Start recording into a new session file if enabled and a device is connected.
+    fn start_recording(&mut self) -> Task<Message> {
+        if !self.config.record_sessions || self.recorder.is_some() {
+            return Task::none();
+        }
+        let Some(device) = self.connected_device().map(|v| v.to_string()) else {
+            return Task::none();
+        };
+        match Recorder::create(&self.config.record_directory, device) {
+            Ok(recorder) => {
+                info!("Recording session to {}", recorder.path().display());
+                self.recorder = Some(recorder);
+                Task::none()
+            }
+            Err(e) => {
+                warn!("Failed to create session file: {e}");
+                Task::done(ErrorOccurred(format!("Failed to create session file: {e}")))
+            }
+        }
+    }
+
+    /// Check the current heart rate against the alarm limits, and notify if an alarm is raised.
+    fn update_alarm(&mut self) -> Task<Message> {
+        let heart_rate = self
+            .heart_rate
+            .as_ref()
+            .filter(|v| v.heart_rate > 0 && !v.contact_lost())
+            .map(|v| v.heart_rate);
+        if !self.alarm_monitor.update(heart_rate, &self.config.alarm) {
+            return Task::none();
+        }
+        if let Some(Err(e)) = self
+            .recorder
+            .as_mut()
+            .map(|r| r.save_alarms(self.alarm_monitor.log()))
+        {
+            warn!("Failed to save alarms: {e}");
+        }
+        let Some(alarm) = self.alarm_monitor.active() else {
+            info!("Heart rate alarm ended");
+            self.alarm_flash = false;
+            return Task::none();
+        };
+        warn!("Heart rate alarm: {:?} {} bpm", alarm.kind, alarm.extreme);
+        self.alarm_flash = true;
+        window::request_user_attention(self.main_window, Some(window::UserAttention::Critical))
+    }
+}
+
+/// Run `f` on the blocking thread pool, so file IO doesn't block the executor.
+async fn blocking<T: Send + 'static>(
+    f: impl FnOnce() -> std::io::Result<T> + Send + 'static,
+) -> std::io::Result<T> {
+    tokio::task::spawn_blocking(f)
+        .await
+        .map_err(std::io::Error::other)?
 }
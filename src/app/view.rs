@@ -1,15 +1,24 @@
-use btleplug::api::CentralState;
+use std::time::{Duration, SystemTime};
+
 use iced::border::rounded;
 use iced::widget::container::rounded_box;
 use iced::widget::{
-    Column, Container, button, center, column, container, pick_list, responsive, right_center, row,
-    rule, slider, space, text, toggler, value,
+    Column, Container, button, canvas, center, column, container, pick_list, responsive,
+    right_center, row, rule, scrollable, slider, space, text, text_input, toggler, value,
 };
 use iced::{Element, Length, window};
 use iced_aw::widget::{labeled_frame, selection_list_with};
 
-use super::{App, ConnectionState, Message};
+use super::chart::{ChartView, SessionChart, Sparkline};
+use super::{App, ConnectionState, Message, Page};
+use crate::alarm::AlarmEvent;
+use crate::datetime::UtcDateTime;
+use crate::export::Format;
+use crate::hrv::HrvWindow;
 use crate::locales::{Language, TranslateItem};
+use crate::replay::Speed;
+use crate::source::SourceState;
+use crate::zones::{Zone, ZoneMethod};
 
 fn themed_container<'a, E: Into<iced::Element<'a, Message>>>(content: E) -> Container<'a, Message> {
     center(content).style(|theme: &iced::Theme| iced::widget::container::Style {
@@ -18,20 +27,69 @@ fn themed_container<'a, E: Into<iced::Element<'a, Message>>>(content: E) -> Cont
     })
 }
 
+/// e.g. `2025-10-09 08:53 UTC`
+fn format_date(time: SystemTime) -> String {
+    let t = UtcDateTime::from(time);
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02} UTC",
+        t.year, t.month, t.day, t.hour, t.minute
+    )
+}
+
+/// Color of the heart rate while it is unreliable, i.e. sensor contact lost or stale
+const UNRELIABLE_COLOR: iced::Color = iced::color!(0x9e9e9e);
+
+/// e.g. `08:53:20`, in UTC
+fn format_time(time: SystemTime) -> String {
+    let t = UtcDateTime::from(time);
+    format!("{:02}:{:02}:{:02}", t.hour, t.minute, t.second)
+}
+
+/// e.g. `High heart rate 08:53:20 - 08:55:02, 172 bpm`
+fn format_alarm(alarm: &AlarmEvent, lang: Language) -> String {
+    format!(
+        "{} {} - {}, {} bpm",
+        TranslateItem::from(alarm.kind).translate(lang),
+        format_time(alarm.started),
+        alarm.ended.map_or_else(|| "...".into(), format_time),
+        alarm.extreme
+    )
+}
+
+/// e.g. `5:07` or `1:05:07`
+pub(super) fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    if secs >= 3600 {
+        format!("{}:{:02}:{:02}", secs / 3600, secs % 3600 / 60, secs % 60)
+    } else {
+        format!("{}:{:02}", secs / 60, secs % 60)
+    }
+}
+
+pub(super) fn zone_color(zone: Zone) -> iced::Color {
+    match zone.0 {
+        0 => iced::color!(0x757575),
+        1 => iced::color!(0x1e88e5),
+        2 => iced::color!(0x43a047),
+        3 => iced::color!(0xfb8c00),
+        _ => iced::color!(0xe53935),
+    }
+}
+
 fn adapter_message<'a>(item: TranslateItem, lang: Language) -> Container<'a, Message> {
     themed_container(text(item.translate(lang)).size(30))
 }
 
 impl App {
     pub fn view(&self, id: window::Id) -> Element<'_, Message> {
-        match (self.adapter_state.clone(), id == self.main_window) {
-            (CentralState::Unknown, true) => {
+        match (self.source_state, id == self.main_window) {
+            (SourceState::Unknown, true) => {
                 adapter_message(TranslateItem::UnknownAdapterState, self.config.lang).into()
             }
-            (CentralState::PoweredOff, true) => {
+            (SourceState::PoweredOff, true) => {
                 adapter_message(TranslateItem::AdapterPowereddOff, self.config.lang).into()
             }
-            (CentralState::Unknown | CentralState::PoweredOff, false) => center("N/A")
+            (SourceState::Unknown | SourceState::PoweredOff, false) => center("N/A")
                 .style(|theme| {
                     let mut style = rounded_box(theme);
                     style.border = rounded(self.config.hr_window_size().height / 2.0);
@@ -45,20 +103,47 @@ impl App {
                     style
                 })
                 .into(),
-            (CentralState::PoweredOn, true) => themed_container(self.main_window_view()).into(),
-            (CentralState::PoweredOn, false) => self.heart_rate_window_view(),
+            (SourceState::PoweredOn, true) => themed_container(self.main_window_view()).into(),
+            (SourceState::PoweredOn, false) => self.heart_rate_window_view(),
         }
     }
 
     fn main_window_view(&self) -> Container<'_, Message> {
+        let page_btn = |page: Page, item: TranslateItem| {
+            button(text(item.translate(self.config.lang)).size(14))
+                .padding([2, 8])
+                .style(if self.page == page {
+                    button::primary
+                } else {
+                    button::text
+                })
+                .on_press(Message::ShowPage(page))
+        };
+        let pages = row![
+            page_btn(Page::Monitor, TranslateItem::MonitorPage),
+            page_btn(Page::History, TranslateItem::HistoryPage)
+        ]
+        .spacing(4);
         let left_pane = Column::new()
             .width(Length::FillPortion(3))
             .spacing(4)
-            .push(match &self.connected_device() {
-                None => self.devices_view(),
-                Some(_) => self.hrm_info_view(),
-            })
-            .push(self.toggle_connect_btn_view());
+            .push(pages)
+            .push(self.alarm_banner_view());
+        let left_pane = match self.page {
+            Page::Monitor => left_pane
+                .push(
+                    self.connected_device()
+                        .is_none()
+                        .then(|| self.summary_view())
+                        .flatten(),
+                )
+                .push(match &self.connected_device() {
+                    None => self.devices_view(),
+                    Some(_) => self.hrm_info_view(),
+                })
+                .push(self.toggle_connect_btn_view()),
+            Page::History => left_pane.push(self.history_view()),
+        };
 
         let mut right_pane = Column::new()
             .height(Length::Fill)
@@ -73,10 +158,119 @@ impl App {
         container(row![left_pane, rule::vertical(1), right_pane].padding(8))
     }
 
+    /// Banner of the active alarm, shown until the heart rate is back within the limits
+    fn alarm_banner_view(&self) -> Option<Element<'_, Message>> {
+        let alarm = self.alarm_monitor.active()?;
+        let lang = self.config.lang;
+        let banner = text!(
+            "⚠ {}: {} bpm, {} {} UTC",
+            TranslateItem::from(alarm.kind).translate(lang),
+            alarm.extreme,
+            TranslateItem::AlarmSinceLabel.translate(lang),
+            format_time(alarm.started)
+        )
+        .size(16);
+        Some(
+            container(banner)
+                .width(Length::Fill)
+                .padding([4, 8])
+                .style(|theme: &iced::Theme| {
+                    let palette = theme.extended_palette();
+                    container::Style {
+                        background: Some(palette.danger.base.color.into()),
+                        text_color: Some(palette.danger.base.text),
+                        border: rounded(4),
+                        ..Default::default()
+                    }
+                })
+                .into(),
+        )
+    }
+
+    /// Alarm log of the current or last connection
+    fn alarm_log_view(&self) -> Option<Element<'_, Message>> {
+        let log = self.alarm_monitor.log();
+        if log.is_empty() {
+            return None;
+        }
+        let lang = self.config.lang;
+        let lines = log
+            .iter()
+            .map(|v| format_alarm(v, lang))
+            .collect::<Vec<_>>()
+            .join("\n");
+        Some(
+            text!("{}\n{lines}", TranslateItem::AlarmsLabel.translate(lang))
+                .size(12)
+                .style(text::danger)
+                .into(),
+        )
+    }
+
+    /// Summary of the last connection
+    fn summary_view(&self) -> Option<Element<'_, Message>> {
+        let summary = self.last_summary.as_ref()?;
+        let lang = self.config.lang;
+        let bpm = |v: Option<u16>| v.map_or_else(|| "--".into(), |v| v.to_string());
+        let zones = summary
+            .time_in_zones
+            .iter()
+            .enumerate()
+            .map(|(i, v)| format!("{} {}", Zone(i), format_duration(*v)))
+            .collect::<Vec<_>>()
+            .join("  ");
+        let energy = summary
+            .energy_expended
+            .map_or_else(|| "N/A".into(), |v| format!("{v} kJ"));
+        let mut lines = vec![
+            format!(
+                "{} {}",
+                TranslateItem::DurationLabel.translate(lang),
+                format_duration(summary.duration)
+            ),
+            format!(
+                "{} {}/{}/{} bpm",
+                TranslateItem::HeartRateRangeLabel.translate(lang),
+                bpm(summary.min_heart_rate),
+                bpm(summary.avg_heart_rate),
+                bpm(summary.max_heart_rate)
+            ),
+            format!(
+                "{} {zones}",
+                TranslateItem::TimeInZonesLabel.translate(lang)
+            ),
+            format!(
+                "{} {energy}",
+                TranslateItem::EnergyExpendedLabel.translate(lang)
+            ),
+            format!(
+                "{} {}",
+                TranslateItem::InvalidPacketsLabel.translate(lang),
+                summary.invalid_packets
+            ),
+        ];
+        if let Some(hrv) = &summary.hrv {
+            lines.push(format!(
+                "HRV: RMSSD {:.0} ms, SDNN {:.0} ms",
+                hrv.rmssd, hrv.sdnn
+            ));
+        }
+
+        Some(
+            labeled_frame::LabeledFrame::new(
+                TranslateItem::SummaryTitle.translate(lang),
+                column![text(lines.join("\n")).size(13)].push(self.alarm_log_view()),
+            )
+            .width(Length::Fill)
+            .stroke_width(1)
+            .into(),
+        )
+    }
+
     fn devices_view(&self) -> Element<'_, Message> {
         let devices = selection_list_with(
             &self.discovered_devices[..],
-            |_, d| Message::SelectDevice(d.address()),
+            |_, d| Message::SelectDevice(d.id()),
             14.0,
             4,
             |theme, status| {
@@ -98,25 +292,314 @@ impl App {
     }
 
     fn hrm_info_view(&self) -> Element<'_, Message> {
-        let hrm_info = center(
-            match self.heart_rate {
-                None => text("--"),
-                Some(hrm) => value(hrm),
-            }
-            .wrapping(text::Wrapping::None),
-        );
+        let hrv_info = self
+            .hrv
+            .metrics()
+            .map(|hrv| value(hrv).wrapping(text::Wrapping::None));
+        let artifacts = self.artifact_filter.artifact_percentage().map(|v| {
+            text!(
+                "{} {v:.1} %",
+                TranslateItem::ArtifactsLabel.translate(self.config.lang)
+            )
+            .style(if v > 5.0 {
+                text::warning
+            } else {
+                text::default
+            })
+        });
+        let contact_lost = self.heart_rate.as_ref().is_some_and(|v| v.contact_lost());
+        let no_contact = contact_lost.then(|| {
+            text!(
+                "⊘ {}",
+                TranslateItem::NoContactLabel.translate(self.config.lang)
+            )
+            .size(20)
+            .style(text::warning)
+        });
+        let stale = self.last_measurement.filter(|_| self.is_stale()).map(|v| {
+            text!(
+                "⌛ {} {} s",
+                TranslateItem::StaleLabel.translate(self.config.lang),
+                v.elapsed().as_secs()
+            )
+            .size(20)
+            .style(text::warning)
+        });
+        let hrm_info = container(
+            row![
+                match &self.heart_rate {
+                    Some(_) if contact_lost && self.config.hide_heart_rate_without_contact => {
+                        text("--")
+                    }
+                    None => text("--"),
+                    Some(hrm) => value(hrm),
+                }
+                .wrapping(text::Wrapping::None)
+            ]
+            .push(column![].push(hrv_info).push(artifacts))
+            .spacing(16),
+        )
+        .center_x(Length::Fill);
 
-        Column::new()
-            .spacing(4)
-            .push(text!(
+        let device = self
+            .connected_device()
+            .expect("[BUG] No device connected, but attempt display heart rate infomation");
+        let location = device.body_sensor_location().map(|v| {
+            text!(
+                "{} {}",
+                TranslateItem::SensorLocationLabel.translate(self.config.lang),
+                TranslateItem::from(v).translate(self.config.lang)
+            )
+        });
+        let battery = device.battery_level().map(|v| {
+            text!(
+                "{} {v} %",
+                TranslateItem::BatteryLabel.translate(self.config.lang)
+            )
+            .style(if v < self.config.battery_warning_level {
+                text::warning
+            } else {
+                text::default
+            })
+        });
+        let details_btn = button(
+            text!(
+                "{} {}",
+                TranslateItem::DeviceDetailsButton.translate(self.config.lang),
+                if self.show_device_details {
+                    "▴"
+                } else {
+                    "▾"
+                }
+            )
+            .size(12),
+        )
+        .padding([0, 4])
+        .style(button::text)
+        .on_press(Message::ToggleDeviceDetails);
+        let recording = self.recorder.as_ref().map(|_| {
+            text(TranslateItem::RecordingLabel.translate(self.config.lang)).style(text::danger)
+        });
+        let header = row![
+            text!(
                 "{} {}",
                 TranslateItem::ConnectedTitle.translate(self.config.lang),
-                self.connected_device()
-                    .expect("[BUG] No device connected, but attempt display heart rate infomation")
-            ))
+                device
+            ),
+            details_btn,
+            space().width(Length::Fill)
+        ]
+        .align_y(iced::Alignment::Center)
+        .push(recording)
+        .push(location)
+        .push(battery)
+        .spacing(8);
+
+        let details = self.show_device_details.then(|| {
+            let info = device
+                .device_information()
+                .map_or_else(|| "N/A".into(), |v| v.to_string());
+            text(info).size(12)
+        });
+
+        Column::new()
+            .spacing(4)
+            .push(header)
+            .push(details)
             .push(rule::horizontal(1))
+            .push(no_contact)
+            .push(stale)
             .push(hrm_info)
+            .push(self.chart_view())
+            .push(self.alarm_log_view())
+            .push(self.playback_view())
+            .into()
+    }
+
+    /// Heart rate chart of the current connection
+    fn chart_view(&self) -> Element<'_, Message> {
+        let lang = self.config.lang;
+        let chart = canvas(SessionChart {
+            series: &self.heart_rate_series,
+            zones: &self.config.hr_zones,
+            view: self.chart_view,
+            show_rr: self.config.chart_show_rr,
+        })
+        .width(Length::Fill)
+        .height(Length::Fill);
+        let controls = row![
+            toggler(self.config.chart_show_rr)
+                .label(TranslateItem::ShowRrSetting.translate(lang))
+                .text_size(13)
+                .on_toggle(Message::ShowRrInChart),
+            space().width(Length::Fill),
+            button(text(TranslateItem::ResetZoomButton.translate(lang)).size(13))
+                .padding([2, 6])
+                .style(button::secondary)
+                .on_press_maybe(
+                    (self.chart_view != ChartView::default())
+                        .then(|| Message::ChartViewChanged(ChartView::default()))
+                )
+        ]
+        .spacing(8)
+        .align_y(iced::Alignment::Center);
+
+        column![chart, controls]
+            .spacing(4)
+            .height(Length::Fill)
+            .into()
+    }
+
+    /// Controls of replaying session, `None` if the connected device is not a replay.
+    fn playback_view(&self) -> Option<Element<'_, Message>> {
+        let playback = self.playback()?;
+        let toggle = if playback.is_paused() {
+            TranslateItem::ResumeButton
+        } else {
+            TranslateItem::PauseButton
+        };
+
+        Some(
+            row![
+                button(text(toggle.translate(self.config.lang)).size(14))
+                    .on_press(Message::TogglePlayback),
+                slider(
+                    0.0..=playback.duration().as_secs_f32(),
+                    playback.position().as_secs_f32(),
+                    Message::PlaybackSeek
+                ),
+                text!(
+                    "{} / {}",
+                    format_duration(playback.position()),
+                    format_duration(playback.duration())
+                )
+                .size(14),
+                pick_list(
+                    Speed::ALL,
+                    Some(playback.speed()),
+                    Message::PlaybackSpeedChanged
+                )
+                .text_size(14),
+            ]
+            .spacing(8)
+            .align_y(iced::Alignment::Center)
+            .into(),
+        )
+    }
+
+    fn history_view(&self) -> Element<'_, Message> {
+        let lang = self.config.lang;
+        let hint = (!self.config.record_sessions)
+            .then(|| text(TranslateItem::HistoryDisabled.translate(lang)).size(12));
+        if self.history.is_empty() {
+            return column![
+                center(text(TranslateItem::HistoryEmpty.translate(lang))).height(Length::Fill)
+            ]
+            .push(hint)
+            .into();
+        }
+
+        let bpm = |v: Option<u16>| v.map_or_else(|| "--".into(), |v| v.to_string());
+        let entries = self.history.iter().map(|entry| {
+            let opened = self.opened_history_entry.as_ref() == Some(&entry.path);
+            button(
+                text!(
+                    "{}  {}  {}/{} bpm  {}",
+                    format_date(entry.start),
+                    format_duration(entry.duration()),
+                    bpm(entry.avg_heart_rate),
+                    bpm(entry.max_heart_rate),
+                    entry.meta.name
+                )
+                .size(13)
+                .wrapping(text::Wrapping::None),
+            )
+            .width(Length::Fill)
+            .padding([2, 4])
+            .style(if opened {
+                button::primary
+            } else {
+                button::text
+            })
+            .on_press(Message::OpenHistoryEntry(entry.path.clone()))
             .into()
+        });
+        let list = scrollable(Column::with_children(entries).spacing(2)).height(Length::Fill);
+
+        let details = self.opened_history_entry().map(|entry| {
+            let rename = row![
+                text_input("", &self.history_name)
+                    .size(14)
+                    .on_input(Message::HistoryNameEdited)
+                    .on_submit(Message::RenameHistoryEntry),
+                button(text(TranslateItem::RenameButton.translate(lang)).size(14))
+                    .on_press(Message::RenameHistoryEntry)
+            ]
+            .spacing(4);
+            let stats = text!(
+                "{} {}\n{} - {}\nmin/avg/max: {}/{}/{} bpm",
+                TranslateItem::DeviceLabel.translate(lang),
+                entry.meta.device.as_deref().unwrap_or("N/A"),
+                format_date(entry.start),
+                format_date(entry.end),
+                bpm(entry.min_heart_rate),
+                bpm(entry.avg_heart_rate),
+                bpm(entry.max_heart_rate)
+            )
+            .size(13);
+            let alarms = (!entry.meta.alarms.is_empty()).then(|| {
+                let alarms = entry
+                    .meta
+                    .alarms
+                    .iter()
+                    .map(|v| format_alarm(v, lang))
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                text!("{}\n{alarms}", TranslateItem::AlarmsLabel.translate(lang))
+                    .size(12)
+                    .style(text::danger)
+            });
+            let delete = if self.confirming_delete {
+                TranslateItem::ConfirmDeleteButton
+            } else {
+                TranslateItem::DeleteButton
+            };
+            let actions = Format::ALL
+                .iter()
+                .fold(
+                    row![text(TranslateItem::ExportLabel.translate(lang)).size(14)],
+                    |actions, format| {
+                        actions.push(
+                            button(text(format.to_string()).size(14))
+                                .style(button::secondary)
+                                .on_press(Message::ExportHistoryEntry(*format)),
+                        )
+                    },
+                )
+                .push(space().width(Length::Fill))
+                .push(
+                    button(text(delete.translate(lang)).size(14))
+                        .style(button::danger)
+                        .on_press(Message::DeleteHistoryEntry),
+                )
+                .spacing(4)
+                .align_y(iced::Alignment::Center);
+            let exported = self.history_exported.as_ref().map(|path| {
+                text!(
+                    "{} {}",
+                    TranslateItem::ExportedLabel.translate(lang),
+                    path.display()
+                )
+                .size(12)
+            });
+            column![rule::horizontal(1), rename, stats]
+                .push(alarms)
+                .push(actions)
+                .push(exported)
+                .spacing(4)
+        });
+
+        column![list].push(details).push(hint).spacing(4).into()
     }
 
     fn toggle_connect_btn_view(&self) -> Element<'_, Message> {
@@ -136,9 +619,22 @@ impl App {
                     .on_press(Message::DisconnectDevice)
             }
         };
-        right_center(row![btn, space().width(Length::Fixed(4.0))])
-            .height(Length::Shrink)
-            .into()
+        let reset_energy = self
+            .connected_device()
+            .filter(|d| d.supports_energy_reset())
+            .map(|_| {
+                button(TranslateItem::ResetEnergyButton.translate(self.config.lang))
+                    .on_press(Message::ResetEnergyExpended)
+            });
+        right_center(
+            row![]
+                .push(reset_energy)
+                .push(btn)
+                .push(space().width(Length::Fixed(4.0)))
+                .spacing(4),
+        )
+        .height(Length::Shrink)
+        .into()
     }
 
     fn settings_view(&self) -> Element<'_, Message> {
@@ -168,6 +664,108 @@ impl App {
             text(TranslateItem::HeartRateWindowOpaqueSetting.translate(self.config.lang)).size(15),
             hr_window_opaque
         ];
+        let show_hrv = toggler(self.config.hr_window_show_hrv)
+            .label(TranslateItem::ShowHrvSetting.translate(self.config.lang))
+            .text_size(font_size)
+            .on_toggle(Message::ShowHrvInHeartRateWindow);
+        let show_battery = toggler(self.config.hr_window_show_battery)
+            .label(TranslateItem::ShowBatterySetting.translate(self.config.lang))
+            .text_size(font_size)
+            .on_toggle(Message::ShowBatteryInHeartRateWindow);
+        let show_trend = toggler(self.config.hr_window_show_trend)
+            .label(TranslateItem::ShowTrendSetting.translate(self.config.lang))
+            .text_size(font_size)
+            .on_toggle(Message::ShowTrendInHeartRateWindow);
+        let trend_window = self.config.hr_window_show_trend.then(|| {
+            column![
+                text!(
+                    "{} {} s",
+                    TranslateItem::TrendWindowSetting.translate(self.config.lang),
+                    self.config.hr_window_trend_secs()
+                )
+                .size(font_size),
+                slider(
+                    30..=300,
+                    self.config.hr_window_trend_secs(),
+                    Message::TrendWindowChanged
+                )
+                .step(30u16)
+            ]
+        });
+        let battery_warning = column![
+            text!(
+                "{} {} %",
+                TranslateItem::BatteryWarningSetting.translate(self.config.lang),
+                self.config.battery_warning_level
+            )
+            .size(font_size),
+            slider(
+                0..=50,
+                self.config.battery_warning_level,
+                Message::BatteryWarningLevelChanged
+            )
+            .step(5u8)
+        ];
+        let hrv_window = row![
+            text(TranslateItem::HrvWindowSetting.translate(self.config.lang)).size(font_size),
+            space().width(Length::Fill),
+            pick_list(
+                HrvWindow::ALL,
+                Some(self.config.hrv_window),
+                Message::HrvWindowChanged
+            )
+            .text_size(font_size)
+        ]
+        .align_y(iced::Alignment::Center);
+        let artifact_threshold = column![
+            text!(
+                "{} {:.0} %",
+                TranslateItem::ArtifactThresholdSetting.translate(self.config.lang),
+                self.config.artifact_threshold() * 100.0
+            )
+            .size(font_size),
+            slider(
+                0.05..=0.5,
+                self.config.artifact_threshold(),
+                Message::ArtifactThresholdChanged
+            )
+            .step(0.01)
+        ];
+        let artifact_correction = toggler(self.config.artifact_correction)
+            .label(TranslateItem::ArtifactCorrectionSetting.translate(self.config.lang))
+            .text_size(font_size)
+            .on_toggle(Message::ArtifactCorrectionToggled);
+        let stale_timeout = column![
+            text!(
+                "{} {} s",
+                TranslateItem::StaleTimeoutSetting.translate(self.config.lang),
+                self.config.stale_timeout()
+            )
+            .size(font_size),
+            slider(
+                2..=60,
+                self.config.stale_timeout(),
+                Message::StaleTimeoutChanged
+            )
+        ];
+        let stale_resubscribe = toggler(self.config.stale_resubscribe)
+            .label(TranslateItem::StaleResubscribeSetting.translate(self.config.lang))
+            .text_size(font_size)
+            .on_toggle(Message::StaleResubscribeToggled);
+        let hide_without_contact = toggler(self.config.hide_heart_rate_without_contact)
+            .label(TranslateItem::NoContactSetting.translate(self.config.lang))
+            .text_size(font_size)
+            .on_toggle(Message::HideHeartRateWithoutContactToggled);
+        let record_sessions = toggler(self.config.record_sessions)
+            .label(TranslateItem::RecordSessionsSetting.translate(self.config.lang))
+            .text_size(font_size)
+            .on_toggle(Message::RecordSessionsToggled);
+        let record_directory = column![
+            text(TranslateItem::RecordDirectorySetting.translate(self.config.lang)).size(font_size),
+            text_input("", &self.config.record_directory.display().to_string())
+                .size(font_size)
+                .on_input(Message::RecordDirectoryChanged)
+        ];
 
         let settings = Column::new()
             .spacing(6)
@@ -176,11 +774,30 @@ impl App {
             .push(rule::horizontal(0.5))
             .push(show_hr_window)
             .push(lock_hr_window)
-            .push(hr_window_opaque);
+            .push(hr_window_opaque)
+            .push(show_hrv)
+            .push(show_battery)
+            .push(show_trend)
+            .push(trend_window)
+            .push(battery_warning)
+            .push(hide_without_contact)
+            .push(stale_timeout)
+            .push(stale_resubscribe)
+            .push(rule::horizontal(0.5))
+            .push(hrv_window)
+            .push(artifact_threshold)
+            .push(artifact_correction)
+            .push(rule::horizontal(0.5))
+            .push(self.zones_settings_view())
+            .push(rule::horizontal(0.5))
+            .push(self.alarm_settings_view())
+            .push(rule::horizontal(0.5))
+            .push(record_sessions)
+            .push(record_directory);
 
         labeled_frame::LabeledFrame::new(
             TranslateItem::SettingsTitle.translate(self.config.lang),
-            settings,
+            scrollable(settings),
         )
         .height(Length::Fill)
         .width(Length::FillPortion(3))
@@ -188,31 +805,230 @@ impl App {
         .into()
     }
 
+    fn zones_settings_view(&self) -> Element<'_, Message> {
+        let font_size = 15;
+        let lang = self.config.lang;
+        let zones = &self.config.hr_zones;
+        let method = row![
+            text(TranslateItem::HrZonesSetting.translate(lang)).size(font_size),
+            space().width(Length::Fill),
+            pick_list(
+                ZoneMethod::ALL,
+                Some(zones.method),
+                Message::ZoneMethodChanged
+            )
+            .text_size(font_size)
+        ]
+        .align_y(iced::Alignment::Center);
+        let max_heart_rate = (zones.method != ZoneMethod::Custom).then(|| {
+            column![
+                text!(
+                    "{} {} bpm",
+                    TranslateItem::MaxHeartRateSetting.translate(lang),
+                    zones.max_heart_rate()
+                )
+                .size(font_size),
+                slider(
+                    100..=240,
+                    zones.max_heart_rate(),
+                    Message::MaxHeartRateChanged
+                )
+            ]
+        });
+        let resting_heart_rate = (zones.method == ZoneMethod::HeartRateReserve).then(|| {
+            column![
+                text!(
+                    "{} {} bpm",
+                    TranslateItem::RestingHeartRateSetting.translate(lang),
+                    zones.resting_heart_rate()
+                )
+                .size(font_size),
+                slider(
+                    30..=120,
+                    zones.resting_heart_rate(),
+                    Message::RestingHeartRateChanged
+                )
+            ]
+        });
+        let (range, unit) = match zones.method {
+            ZoneMethod::Custom => (40..=240, "bpm"),
+            _ => (30..=100, "%"),
+        };
+        let bounds = zones
+            .raw_bounds()
+            .into_iter()
+            .zip(zones.bounds())
+            .enumerate()
+            .map(|(i, (raw, bpm))| {
+                let zone = Zone(i);
+                let label = if zones.method == ZoneMethod::Custom {
+                    format!("{zone} ≥ {raw} bpm")
+                } else {
+                    format!("{zone} ≥ {raw} {unit} ({bpm} bpm)")
+                };
+                column![
+                    text(label).size(13).color(zone_color(zone)),
+                    slider(range.clone(), raw, move |v| Message::ZoneBoundChanged(
+                        zone, v
+                    ))
+                ]
+                .into()
+            });
+        let zone_colors = toggler(self.config.hr_window_zone_colors)
+            .label(TranslateItem::ZoneColorsSetting.translate(lang))
+            .text_size(font_size)
+            .on_toggle(Message::ZoneColorsToggled);
+
+        column![method]
+            .push(max_heart_rate)
+            .push(resting_heart_rate)
+            .extend(bounds)
+            .push(zone_colors)
+            .spacing(6)
+            .into()
+    }
+
+    fn alarm_settings_view(&self) -> Element<'_, Message> {
+        let font_size = 15;
+        let lang = self.config.lang;
+        let alarm = &self.config.alarm;
+        let enabled = toggler(alarm.enabled)
+            .label(TranslateItem::AlarmsSetting.translate(lang))
+            .text_size(font_size)
+            .on_toggle(Message::AlarmsToggled);
+        let limits = alarm.enabled.then(|| {
+            column![
+                text!(
+                    "{} {} bpm",
+                    TranslateItem::AlarmHighSetting.translate(lang),
+                    alarm.high
+                )
+                .size(font_size),
+                slider(100..=240, alarm.high, Message::AlarmHighChanged),
+                text!(
+                    "{} {} bpm",
+                    TranslateItem::AlarmLowSetting.translate(lang),
+                    alarm.low
+                )
+                .size(font_size),
+                slider(30..=120, alarm.low, Message::AlarmLowChanged),
+                text!(
+                    "{} {} bpm",
+                    TranslateItem::AlarmHysteresisSetting.translate(lang),
+                    alarm.hysteresis
+                )
+                .size(font_size),
+                slider(0..=20, alarm.hysteresis, Message::AlarmHysteresisChanged),
+                text!(
+                    "{} {} s",
+                    TranslateItem::AlarmMinDurationSetting.translate(lang),
+                    alarm.min_duration
+                )
+                .size(font_size),
+                slider(
+                    0..=120,
+                    alarm.min_duration,
+                    Message::AlarmMinDurationChanged
+                )
+                .step(5u16),
+            ]
+            .spacing(2)
+        });
+
+        column![enabled].push(limits).spacing(6).into()
+    }
+
     fn heart_rate_window_view(&self) -> Element<'_, Message> {
         responsive(move |size| {
             let font_size = size.height / 1.6;
-            let icon = text("❤ ").size(font_size);
+            let contact_lost = self.heart_rate.as_ref().is_some_and(|v| v.contact_lost());
+            let stale = self.is_stale();
+            let icon = match (stale, contact_lost) {
+                (true, _) => "⌛ ",
+                (false, true) => "⊘ ",
+                (false, false) => "❤ ",
+            };
+            let icon = text(icon).size(font_size);
             let rate = self
                 .heart_rate
+                .as_ref()
+                .filter(|_| !(contact_lost && self.config.hide_heart_rate_without_contact))
                 .map(|v| v.heart_rate.to_string())
                 .unwrap_or_else(|| "--".into());
-            let rate = text(rate).size(font_size).font(iced::Font {
-                weight: iced::font::Weight::Bold,
-                ..Default::default()
+            let rate = text(rate)
+                .size(font_size)
+                .font(iced::Font {
+                    weight: iced::font::Weight::Bold,
+                    ..Default::default()
+                })
+                .color_maybe((stale || contact_lost).then_some(UNRELIABLE_COLOR));
+            let hrv = self.config.hr_window_show_hrv.then(|| {
+                let rmssd = self
+                    .hrv
+                    .metrics()
+                    .map(|v| format!("{:.0}", v.rmssd))
+                    .unwrap_or_else(|| "--".into());
+                column![
+                    text("HRV").size(font_size / 3.0),
+                    text(rmssd).size(font_size / 2.0)
+                ]
+                .align_x(iced::Alignment::Center)
             });
-            let content = row![icon, rate, space().width(5)];
+            let battery = self.config.hr_window_show_battery.then(|| {
+                let level = self.connected_device().and_then(|d| d.battery_level());
+                column![
+                    text("BAT").size(font_size / 3.0),
+                    text(level.map_or_else(|| "--".into(), |v| format!("{v}%")))
+                        .size(font_size / 2.0)
+                        .style(move |theme| match level {
+                            Some(v) if v < self.config.battery_warning_level => {
+                                text::danger(theme)
+                            }
+                            _ => text::default(theme),
+                        })
+                ]
+                .align_x(iced::Alignment::Center)
+            });
+            let trend = self.config.hr_window_show_trend.then(|| {
+                canvas(Sparkline {
+                    series: &self.heart_rate_series,
+                    window: Duration::from_secs(self.config.hr_window_trend_secs() as u64),
+                    color: if stale {
+                        UNRELIABLE_COLOR
+                    } else {
+                        iced::Color::WHITE
+                    },
+                    fill_alpha: self.config.hr_window_opaque * 0.5,
+                })
+                .width(self.config.hr_window_trend_width())
+                .height(size.height * 0.6)
+            });
+            let content = row![icon, rate, space().width(5)]
+                .push(trend)
+                .push(hrv)
+                .push(battery)
+                .align_y(iced::Alignment::Center);
+            let zone = self
+                .heart_rate
+                .as_ref()
+                .filter(|_| self.config.hr_window_zone_colors && !stale)
+                .filter(|v| !v.contact_lost())
+                .and_then(|v| self.config.hr_zones.zone(v.heart_rate));
+            let flashing = self.alarm_monitor.active().is_some() && self.alarm_flash;
             center(content)
                 .padding(5)
                 .style(move |theme| {
                     let mut style = rounded_box(theme);
                     style.border = rounded(size.height / 2.0);
-                    style.background = Some(
+                    style.background = Some(if flashing {
+                        theme.extended_palette().danger.strong.color.into()
+                    } else {
                         iced::Color {
                             a: self.config.hr_window_opaque,
-                            ..iced::Color::BLACK
+                            ..zone.map_or(iced::Color::BLACK, zone_color)
                         }
-                        .into(),
-                    );
+                        .into()
+                    });
                     style
                 })
                 .into()
@@ -0,0 +1,210 @@
+use std::collections::VecDeque;
+
+/// RR-Intervals out of this range (unit: ms) are always considered as artifact
+const PHYSIOLOGICAL_RANGE: std::ops::RangeInclusive<f32> = 250.0..=2000.0;
+/// Number of recent accepted intervals used to compute the local median
+const MEDIAN_LEN: usize = 11;
+/// Before collected this number of intervals, only the physiological range is checked
+const MIN_MEDIAN_LEN: usize = 5;
+/// Number of recent intervals used to compute the artifact percentage
+const REPORT_LEN: usize = 100;
+
+/// RR-Interval artifact (missed, extra or ectopic beats) filter.
+///
+/// An interval is flagged as artifact if it deviates from the median of recent accepted intervals
+/// by more than `threshold` (relative to the median). If correction is enabled, a flagged interval
+/// of several missed beats is split into that many intervals, others are replaced by the local
+/// median. Otherwise it is dropped.
+#[derive(Debug, Clone)]
+pub struct ArtifactFilter {
+    threshold: f32,
+    correction: bool,
+    /// Recent accepted intervals, unit: ms
+    recent: VecDeque<f32>,
+    /// Whether the recent intervals are artifact
+    flags: VecDeque<bool>,
+    /// Number of consecutive artifacts. Too many consecutive artifacts means the rhythm really
+    /// changed, the local median will be re-learned.
+    consecutive: usize,
+}
+
+impl ArtifactFilter {
+    pub fn new(threshold: f32, correction: bool) -> Self {
+        Self {
+            threshold,
+            correction,
+            recent: VecDeque::with_capacity(MEDIAN_LEN),
+            flags: VecDeque::with_capacity(REPORT_LEN),
+            consecutive: 0,
+        }
+    }
+
+    pub fn set_threshold(&mut self, threshold: f32) {
+        self.threshold = threshold;
+    }
+
+    pub fn set_correction(&mut self, correction: bool) {
+        self.correction = correction;
+    }
+
+    /// Filter a RR-Interval (unit: ms), return the values should be used by consumers, empty if
+    /// it is dropped.
+    pub fn process(&mut self, rr: f32) -> Vec<f32> {
+        let median = self.median();
+        let is_artifact = !PHYSIOLOGICAL_RANGE.contains(&rr)
+            || median.is_some_and(|m| (rr - m).abs() > m * self.threshold);
+
+        if self.flags.len() == REPORT_LEN {
+            self.flags.pop_front();
+        }
+        self.flags.push_back(is_artifact);
+
+        self.consecutive = if is_artifact { self.consecutive + 1 } else { 0 };
+        if self.consecutive >= MEDIAN_LEN {
+            self.recent.clear();
+            self.consecutive = 0;
+        }
+
+        if !is_artifact {
+            if self.recent.len() == MEDIAN_LEN {
+                self.recent.pop_front();
+            }
+            self.recent.push_back(rr);
+            vec![rr]
+        } else if self.correction
+            && let Some(median) = median
+        {
+            self.correct(rr, median)
+        } else {
+            Vec::new()
+        }
+    }
+
+    /// Percentage of artifacts in recent intervals, `None` if no interval processed yet.
+    pub fn artifact_percentage(&self) -> Option<f32> {
+        (!self.flags.is_empty()).then(|| {
+            self.flags.iter().filter(|v| **v).count() as f32 * 100.0 / self.flags.len() as f32
+        })
+    }
+
+    pub fn clear(&mut self) {
+        self.recent.clear();
+        self.flags.clear();
+        self.consecutive = 0;
+    }
+
+    /// Split an interval of missed beats into equal intervals, or replace it by the median.
+    fn correct(&self, rr: f32, median: f32) -> Vec<f32> {
+        let beats = (rr / median).round();
+        let split = rr / beats;
+        if beats >= 2.0 && (split - median).abs() <= median * self.threshold {
+            vec![split; beats as usize]
+        } else {
+            vec![median]
+        }
+    }
+
+    fn median(&self) -> Option<f32> {
+        if self.recent.len() < MIN_MEDIAN_LEN {
+            return None;
+        }
+        let mut sorted = self.recent.iter().copied().collect::<Vec<_>>();
+        sorted.sort_by(f32::total_cmp);
+        let mid = sorted.len() / 2;
+        Some(if sorted.len().is_multiple_of(2) {
+            (sorted[mid - 1] + sorted[mid]) / 2.0
+        } else {
+            sorted[mid]
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A filter learned the median of 800 ms
+    fn learned(correction: bool) -> ArtifactFilter {
+        let mut filter = ArtifactFilter::new(0.2, correction);
+        for rr in [790.0, 800.0, 810.0, 800.0, 795.0, 805.0] {
+            assert_eq!(filter.process(rr), [rr]);
+        }
+        filter
+    }
+
+    #[test]
+    fn physiological_range_before_median_learned() {
+        let mut filter = ArtifactFilter::new(0.2, true);
+        assert_eq!(filter.process(200.0), [0.0; 0]);
+        assert_eq!(filter.process(2100.0), [0.0; 0]);
+        assert_eq!(filter.process(1500.0), [1500.0]);
+        assert_eq!(filter.process(500.0), [500.0]);
+    }
+
+    #[test]
+    fn threshold() {
+        let mut filter = learned(false);
+        // Median is 800 ms, the threshold is 160 ms
+        assert_eq!(filter.process(960.0), [960.0]);
+        assert_eq!(filter.process(640.0), [640.0]);
+        assert_eq!(filter.process(961.0), [0.0; 0]);
+        assert_eq!(filter.process(639.0), [0.0; 0]);
+
+        filter.set_threshold(0.5);
+        assert_eq!(filter.process(1100.0), [1100.0]);
+    }
+
+    #[test]
+    fn replace_by_median() {
+        let mut filter = learned(true);
+        // Extra beat
+        assert_eq!(filter.process(400.0), [800.0]);
+        assert_eq!(filter.process(1100.0), [800.0]);
+        // Correction doesn't change the median
+        assert_eq!(filter.process(990.0), [800.0]);
+    }
+
+    #[test]
+    fn split_missed_beats() {
+        let mut filter = learned(true);
+        assert_eq!(filter.process(1620.0), [810.0, 810.0]);
+        assert_eq!(filter.process(2400.0), [800.0, 800.0, 800.0]);
+        // Not a multiple of the median
+        assert_eq!(filter.process(1250.0), [800.0]);
+
+        filter.set_correction(false);
+        assert_eq!(filter.process(1600.0), [0.0; 0]);
+    }
+
+    #[test]
+    fn relearn_after_consecutive_artifacts() {
+        let mut filter = learned(false);
+        // The rhythm really changed
+        for _ in 0..MEDIAN_LEN {
+            assert_eq!(filter.process(500.0), [0.0; 0]);
+        }
+        assert_eq!(filter.process(500.0), [500.0]);
+        for _ in 0..MIN_MEDIAN_LEN {
+            filter.process(500.0);
+        }
+        assert_eq!(filter.process(800.0), [0.0; 0]);
+    }
+
+    #[test]
+    fn artifact_percentage() {
+        let mut filter = learned(false);
+        assert_eq!(filter.artifact_percentage(), Some(0.0));
+        filter.process(400.0);
+        filter.process(400.0);
+        assert_eq!(filter.artifact_percentage(), Some(25.0));
+
+        // Only recent intervals are counted
+        for _ in 0..REPORT_LEN {
+            filter.process(800.0);
+        }
+        assert_eq!(filter.artifact_percentage(), Some(0.0));
+
+        filter.clear();
+        assert_eq!(filter.artifact_percentage(), None);
+    }
+}
@@ -3,25 +3,73 @@ use std::path::PathBuf;
 
 use serde::{Deserialize, Serialize};
 
+use crate::alarm::AlarmSettings;
+use crate::hrv::HrvWindow;
 use crate::locales::Language;
+use crate::zones::HrZones;
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone)]
 pub struct Config {
     hr_window_scale: f32,
     pub hr_window_pos: iced::Point,
     pub hr_window_visible: bool,
     pub hr_window_locked: bool,
     pub hr_window_opaque: f32,
+    pub hr_window_show_hrv: bool,
+    pub hr_window_show_battery: bool,
+    pub hr_window_show_trend: bool,
+    /// Time span of the trend in heart rate window, unit: seconds
+    hr_window_trend_secs: u16,
+    /// Color the heart rate window by the current heart rate zone
+    pub hr_window_zone_colors: bool,
+    /// Hide the heart rate while the device reports no sensor contact
+    pub hide_heart_rate_without_contact: bool,
+    /// The heart rate is considered stale if no measurement is received for this long, unit:
+    /// seconds
+    stale_timeout: u8,
+    /// Resubscribe the heart rate automatically when it is stale
+    pub stale_resubscribe: bool,
+    /// Warn when the battery level of connected device drops below it, unit: %
+    pub battery_warning_level: u8,
+    pub hrv_window: HrvWindow,
+    artifact_threshold: f32,
+    pub artifact_correction: bool,
+    pub hr_zones: HrZones,
+    pub alarm: AlarmSettings,
+    /// Show RR-Intervals in the heart rate chart of main window
+    pub chart_show_rr: bool,
+    /// Record each connection into a session file
+    pub record_sessions: bool,
+    /// Where the session files are saved
+    pub record_directory: PathBuf,
     pub lang: Language,
 }
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 struct ConfigSerdeable {
     pub hr_window_pos: (f32, f32),
     pub hr_window_scale: f32,
     pub hr_window_visible: bool,
     pub hr_window_locked: bool,
     pub hr_window_opaque: f32,
+    pub hr_window_show_hrv: bool,
+    pub hr_window_show_battery: bool,
+    pub hr_window_show_trend: bool,
+    pub hr_window_trend_secs: u16,
+    pub hr_window_zone_colors: bool,
+    pub hide_heart_rate_without_contact: bool,
+    pub stale_timeout: u8,
+    pub stale_resubscribe: bool,
+    pub battery_warning_level: u8,
+    pub hrv_window: HrvWindow,
+    pub artifact_threshold: f32,
+    pub artifact_correction: bool,
+    pub hr_zones: HrZones,
+    pub alarm: AlarmSettings,
+    pub chart_show_rr: bool,
+    pub record_sessions: bool,
+    pub record_directory: PathBuf,
     pub lang: Language,
 }
 
@@ -56,11 +104,29 @@ fn config_path() -> PathBuf {
     PathBuf::from(name)
 }
 
+fn default_record_directory() -> PathBuf {
+    let name = "hr-view-sessions";
+    #[cfg(target_os = "windows")]
+    let home = env::var("USERPROFILE");
+    #[cfg(not(target_os = "windows"))]
+    let home = env::var("HOME");
+    match home {
+        Ok(home) => PathBuf::from(home).join(name),
+        Err(_) => PathBuf::from(name),
+    }
+}
+
 impl Config {
     const DEFAULT_SIZE: iced::Size = iced::Size {
         width: 120.0,
         height: 50.0,
     };
+    /// Extra width of heart rate window to display HRV
+    const HRV_WIDTH: f32 = 50.0;
+    /// Extra width of heart rate window to display battery level
+    const BATTERY_WIDTH: f32 = 50.0;
+    /// Extra width of heart rate window to display trend
+    const TREND_WIDTH: f32 = 80.0;
 
     pub fn load() -> Option<Self> {
         let config = std::fs::read_to_string(config_path()).ok()?;
@@ -72,7 +138,7 @@ impl Config {
     }
 
     pub fn save(&self) {
-        if let Ok(config) = serde_json::to_string(&ConfigSerdeable::from(*self)) {
+        if let Ok(config) = serde_json::to_string(&ConfigSerdeable::from(self.clone())) {
             let _ = std::fs::write(config_path(), config);
         }
     }
@@ -86,8 +152,53 @@ impl Config {
         self.hr_window_scale
     }
 
+    /// Relative deviation from local median that a RR-Interval is considered as artifact
+    pub fn artifact_threshold(&self) -> f32 {
+        self.artifact_threshold
+    }
+
+    pub fn set_artifact_threshold(&mut self, value: f32) -> f32 {
+        self.artifact_threshold = value.clamp(0.05, 0.5);
+        self.artifact_threshold
+    }
+
+    /// Unit: seconds
+    pub fn stale_timeout(&self) -> u8 {
+        self.stale_timeout
+    }
+
+    pub fn set_stale_timeout(&mut self, secs: u8) -> u8 {
+        self.stale_timeout = secs.clamp(2, 60);
+        self.stale_timeout
+    }
+
+    /// Unit: seconds
+    pub fn hr_window_trend_secs(&self) -> u16 {
+        self.hr_window_trend_secs
+    }
+
+    pub fn set_hr_window_trend_secs(&mut self, secs: u16) -> u16 {
+        self.hr_window_trend_secs = secs.clamp(30, 300);
+        self.hr_window_trend_secs
+    }
+
+    /// Width of the trend in heart rate window
+    pub fn hr_window_trend_width(&self) -> f32 {
+        Self::TREND_WIDTH * self.hr_window_scale
+    }
+
     pub fn hr_window_size(&self) -> iced::Size {
-        Self::DEFAULT_SIZE * self.hr_window_scale
+        let mut size = Self::DEFAULT_SIZE;
+        if self.hr_window_show_hrv {
+            size.width += Self::HRV_WIDTH;
+        }
+        if self.hr_window_show_battery {
+            size.width += Self::BATTERY_WIDTH;
+        }
+        if self.hr_window_show_trend {
+            size.width += Self::TREND_WIDTH;
+        }
+        size * self.hr_window_scale
     }
 }
 
@@ -99,6 +210,23 @@ impl Default for Config {
             hr_window_visible: true,
             hr_window_locked: false,
             hr_window_opaque: 0.5,
+            hr_window_show_hrv: false,
+            hr_window_show_battery: false,
+            hr_window_show_trend: false,
+            hr_window_trend_secs: 120,
+            hr_window_zone_colors: true,
+            hide_heart_rate_without_contact: true,
+            stale_timeout: 5,
+            stale_resubscribe: true,
+            battery_warning_level: 20,
+            hrv_window: HrvWindow::default(),
+            artifact_threshold: 0.2,
+            artifact_correction: true,
+            hr_zones: HrZones::default(),
+            alarm: AlarmSettings::default(),
+            chart_show_rr: false,
+            record_sessions: false,
+            record_directory: default_record_directory(),
             lang: sys_locale::get_locale()
                 .map(|v| Language::from(v.as_str()))
                 .unwrap_or_default(),
@@ -106,6 +234,12 @@ impl Default for Config {
     }
 }
 
+impl Default for ConfigSerdeable {
+    fn default() -> Self {
+        Config::default().into()
+    }
+}
+
 impl From<ConfigSerdeable> for Config {
     fn from(value: ConfigSerdeable) -> Self {
         let mut config = Self {
@@ -113,10 +247,30 @@ impl From<ConfigSerdeable> for Config {
             hr_window_visible: value.hr_window_visible,
             hr_window_locked: value.hr_window_locked,
             hr_window_opaque: value.hr_window_opaque.clamp(0.0, 1.0),
+            hr_window_show_hrv: value.hr_window_show_hrv,
+            hr_window_show_battery: value.hr_window_show_battery,
+            hr_window_show_trend: value.hr_window_show_trend,
+            hr_window_zone_colors: value.hr_window_zone_colors,
+            hide_heart_rate_without_contact: value.hide_heart_rate_without_contact,
+            stale_resubscribe: value.stale_resubscribe,
+            battery_warning_level: value.battery_warning_level.min(100),
+            hrv_window: value.hrv_window,
+            artifact_correction: value.artifact_correction,
+            hr_zones: value.hr_zones,
+            alarm: value.alarm,
+            chart_show_rr: value.chart_show_rr,
+            record_sessions: value.record_sessions,
+            record_directory: value.record_directory,
             lang: value.lang,
             ..Default::default()
         };
         config.set_hr_window_scale(value.hr_window_scale);
+        config.set_artifact_threshold(value.artifact_threshold);
+        config.set_stale_timeout(value.stale_timeout);
+        config.set_hr_window_trend_secs(value.hr_window_trend_secs);
+        let max_heart_rate = config.hr_zones.max_heart_rate();
+        config.hr_zones.set_max_heart_rate(max_heart_rate);
+        config.alarm.set_limits(config.alarm.high, config.alarm.low);
         config
     }
 }
@@ -129,6 +283,23 @@ impl From<Config> for ConfigSerdeable {
             hr_window_visible: value.hr_window_visible,
             hr_window_locked: value.hr_window_locked,
             hr_window_opaque: value.hr_window_opaque,
+            hr_window_show_hrv: value.hr_window_show_hrv,
+            hr_window_show_battery: value.hr_window_show_battery,
+            hr_window_show_trend: value.hr_window_show_trend,
+            hr_window_trend_secs: value.hr_window_trend_secs,
+            hr_window_zone_colors: value.hr_window_zone_colors,
+            hide_heart_rate_without_contact: value.hide_heart_rate_without_contact,
+            stale_timeout: value.stale_timeout,
+            stale_resubscribe: value.stale_resubscribe,
+            battery_warning_level: value.battery_warning_level,
+            hrv_window: value.hrv_window,
+            artifact_threshold: value.artifact_threshold,
+            artifact_correction: value.artifact_correction,
+            hr_zones: value.hr_zones,
+            alarm: value.alarm,
+            chart_show_rr: value.chart_show_rr,
+            record_sessions: value.record_sessions,
+            record_directory: value.record_directory,
             lang: value.lang,
         }
     }
@@ -0,0 +1,116 @@
+//! Minimal UTC calendar conversion, for file names and timestamps of exported files.
+
+use std::fmt::Display;
+use std::time::{Duration, SystemTime};
+
+/// A UTC date and time, `Display` in ISO 8601 format, e.g. `2025-10-09T08:53:20.000Z`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct UtcDateTime {
+    pub year: i64,
+    pub month: u8,
+    pub day: u8,
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+    pub millisecond: u16,
+}
+
+impl UtcDateTime {
+    /// Parse an ISO 8601 date and time with a time zone designator, as used by XML schemas, e.g.
+    /// `2025-10-09T08:53:20Z` or `2025-10-09T10:53:20.5+02:00`.
+    pub fn parse(text: &str) -> Option<SystemTime> {
+        let text = text.trim();
+        let number = |range: std::ops::Range<usize>| text.get(range)?.parse::<i64>().ok();
+        let (year, month, day) = (number(0..4)?, number(5..7)?, number(8..10)?);
+        let (hour, minute, second) = (number(11..13)?, number(14..16)?, number(17..19)?);
+        if text.get(4..5)? != "-" || !text.get(10..11)?.eq_ignore_ascii_case("T") {
+            return None;
+        }
+
+        let mut rest = &text[19..];
+        let mut nanos = 0u32;
+        if let Some(fraction) = rest.strip_prefix('.') {
+            let digits = fraction
+                .find(|c: char| !c.is_ascii_digit())
+                .unwrap_or(fraction.len());
+            let padded = format!("{:0<9}", &fraction[..digits.min(9)]);
+            nanos = padded.parse().ok()?;
+            rest = &fraction[digits..];
+        }
+        let offset = match rest {
+            "Z" | "z" => 0,
+            _ => {
+                let sign = match rest.get(..1)? {
+                    "+" => 1,
+                    "-" => -1,
+                    _ => return None,
+                };
+                let hours = rest.get(1..3)?.parse::<i64>().ok()?;
+                let minutes = rest.get(4..6)?.parse::<i64>().ok()?;
+                sign * (hours * 3600 + minutes * 60)
+            }
+        };
+
+        // Days from civil, see http://howardhinnant.github.io/date_algorithms.html
+        let y = if month <= 2 { year - 1 } else { year };
+        let era = y.div_euclid(400);
+        let yoe = y.rem_euclid(400);
+        let mp = (month + 9) % 12;
+        let doy = (153 * mp + 2) / 5 + day - 1;
+        let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+        let days = era * 146_097 + doe - 719_468;
+
+        let secs = days * 86400 + hour * 3600 + minute * 60 + second - offset;
+        let secs = u64::try_from(secs).ok()?;
+        Some(SystemTime::UNIX_EPOCH + Duration::new(secs, nanos))
+    }
+
+    /// Compact form for file names, e.g. `20251009-085320`
+    pub fn file_stamp(&self) -> String {
+        format!(
+            "{:04}{:02}{:02}-{:02}{:02}{:02}",
+            self.year, self.month, self.day, self.hour, self.minute, self.second
+        )
+    }
+}
+
+impl From<SystemTime> for UtcDateTime {
+    fn from(value: SystemTime) -> Self {
+        let since_epoch = value
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or(Duration::ZERO);
+        let secs = since_epoch.as_secs() as i64;
+        let (days, secs_of_day) = (secs.div_euclid(86400), secs.rem_euclid(86400));
+
+        // Civil from days, see http://howardhinnant.github.io/date_algorithms.html
+        let z = days + 719_468;
+        let era = z.div_euclid(146_097);
+        let doe = z.rem_euclid(146_097);
+        let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = (doy - (153 * mp + 2) / 5 + 1) as u8;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 } as u8;
+        let year = yoe + era * 400 + i64::from(month <= 2);
+
+        Self {
+            year,
+            month,
+            day,
+            hour: (secs_of_day / 3600) as u8,
+            minute: (secs_of_day % 3600 / 60) as u8,
+            second: (secs_of_day % 60) as u8,
+            millisecond: since_epoch.subsec_millis() as u16,
+        }
+    }
+}
+
+impl Display for UtcDateTime {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+            self.year, self.month, self.day, self.hour, self.minute, self.second, self.millisecond
+        )
+    }
+}
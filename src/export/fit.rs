@@ -0,0 +1,216 @@
+//! FIT activity file, see [`crate::fit`]
+
+use std::time::SystemTime;
+
+use super::Summary;
+use crate::fit::{self, Field, Message};
+use crate::session::Session;
+
+mod global {
+    pub const FILE_ID: u16 = 0;
+    pub const SESSION: u16 = 18;
+    pub const LAP: u16 = 19;
+    pub const RECORD: u16 = 20;
+    pub const EVENT: u16 = 21;
+    pub const ACTIVITY: u16 = 34;
+    pub const HRV: u16 = 78;
+}
+
+/// `manufacturer` value for development
+const MANUFACTURER_DEVELOPMENT: u16 = 255;
+/// Values of a `hrv` message, unused ones are invalid (`0xFFFF`)
+const HRV_VALUES: usize = 5;
+
+/// A FIT activity with `record` (timestamp and heart rate) and `hrv` (RR-Intervals) messages,
+/// followed by a lap, a session and an activity summary.
+pub fn encode(session: &Session) -> Result<Vec<u8>, fit::FitError> {
+    let timestamp = |t: SystemTime| {
+        let secs = t
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map_or(0, |v| v.as_secs());
+        fit::from_unix_secs(secs)
+    };
+    let start = session
+        .samples
+        .first()
+        .map_or(SystemTime::UNIX_EPOCH, |v| v.wall_clock);
+    let start = timestamp(start);
+    let end = session
+        .samples
+        .last()
+        .map_or(start, |v| timestamp(v.wall_clock));
+    let elapsed_ms = (session.duration().as_millis()).min(u32::MAX as u128) as u32;
+    let summary = Summary::of(session);
+
+    let mut messages = vec![
+        Message::new(global::FILE_ID)
+            // Activity
+            .with(Field::enumeration(0, 4))
+            .with(Field::uint16(1, MANUFACTURER_DEVELOPMENT))
+            .with(Field::uint16(2, 0))
+            .with(Field::uint32(4, start)),
+        timer_event(start, 0),
+    ];
+    for sample in &session.samples {
+        let mut record = Message::new(global::RECORD).with(Field::uint32(
+            fit::TIMESTAMP_FIELD,
+            timestamp(sample.wall_clock),
+        ));
+        // 0xFF is invalid
+        if let Some(hr) = super::heart_rate_bpm(&sample.hrm).filter(|v| *v < 0xFF) {
+            record.set_field(Field::uint8(3, hr));
+        }
+        messages.push(record);
+
+        let rr_intervals = sample
+            .hrm
+            .rr_intervals_ms()
+            .map(|v| v.round() as u16)
+            .collect::<Vec<_>>();
+        for chunk in rr_intervals.chunks(HRV_VALUES) {
+            let mut values = [u16::MAX; HRV_VALUES];
+            values[..chunk.len()].copy_from_slice(chunk);
+            // Unit: s, scale: 1000
+            messages.push(Message::new(global::HRV).with(Field::uint16_array(0, &values)));
+        }
+    }
+    // Stop all
+    messages.push(timer_event(end, 4));
+
+    let average_hr = summary.average_heart_rate.unwrap_or(0xFF);
+    let max_hr = summary.max_heart_rate.unwrap_or(0xFF);
+    messages.push(
+        Message::new(global::LAP)
+            .with(Field::uint32(fit::TIMESTAMP_FIELD, end))
+            .with(Field::enumeration(0, 9))
+            .with(Field::enumeration(1, 1))
+            .with(Field::uint32(2, start))
+            .with(Field::uint32(7, elapsed_ms))
+            .with(Field::uint32(8, elapsed_ms))
+            .with(Field::uint16(11, summary.calories))
+            .with(Field::uint8(15, average_hr))
+            .with(Field::uint8(16, max_hr)),
+    );
+    messages.push(
+        Message::new(global::SESSION)
+            .with(Field::uint32(fit::TIMESTAMP_FIELD, end))
+            .with(Field::enumeration(0, 8))
+            .with(Field::enumeration(1, 1))
+            .with(Field::uint32(2, start))
+            // Generic sport and sub sport
+            .with(Field::enumeration(5, 0))
+            .with(Field::enumeration(6, 0))
+            .with(Field::uint32(7, elapsed_ms))
+            .with(Field::uint32(8, elapsed_ms))
+            .with(Field::uint16(11, summary.calories))
+            .with(Field::uint8(16, average_hr))
+            .with(Field::uint8(17, max_hr))
+            .with(Field::uint16(25, 0))
+            .with(Field::uint16(26, 1)),
+    );
+    messages.push(
+        Message::new(global::ACTIVITY)
+            .with(Field::uint32(fit::TIMESTAMP_FIELD, end))
+            .with(Field::uint32(0, elapsed_ms))
+            .with(Field::uint16(1, 1))
+            // Manual
+            .with(Field::enumeration(2, 0))
+            .with(Field::enumeration(3, 26))
+            .with(Field::enumeration(4, 1)),
+    );
+    fit::encode(&messages)
+}
+
+/// Event of the timer, `event_type` is `0` for start or `4` for stop all.
+fn timer_event(timestamp: u32, event_type: u8) -> Message {
+    Message::new(global::EVENT)
+        .with(Field::uint32(fit::TIMESTAMP_FIELD, timestamp))
+        .with(Field::enumeration(0, 0))
+        .with(Field::enumeration(1, event_type))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::num::NonZeroU16;
+
+    use super::*;
+    use crate::export::test_session;
+    use crate::fit::FitError;
+
+    /// 2025-01-02T03:04:05Z
+    const START: u32 = 1_735_787_045 - fit::FIT_EPOCH_OFFSET as u32;
+
+    fn value(message: &Message, number: u8) -> Option<u32> {
+        message.field(number).and_then(Field::as_u32)
+    }
+
+    fn messages(messages: &[Message], global: u16) -> Vec<&Message> {
+        messages.iter().filter(|v| v.global == global).collect()
+    }
+
+    #[test]
+    fn decode_encoded_session() {
+        let mut session = test_session(&[80, 0, 120]);
+        session.samples[2].hrm.rr_intervals = [1024, 512]
+            .into_iter()
+            .filter_map(NonZeroU16::new)
+            .collect();
+        let decoded = fit::decode(&encode(&session).unwrap()).unwrap();
+
+        let records = messages(&decoded, global::RECORD);
+        let timestamps = records.iter().map(|v| v.timestamp()).collect::<Vec<_>>();
+        assert_eq!(timestamps, [Some(START), Some(START + 1), Some(START + 2)]);
+        let heart_rates = records.iter().map(|v| value(v, 3)).collect::<Vec<_>>();
+        assert_eq!(heart_rates, [Some(80), None, Some(120)]);
+
+        let hrv = messages(&decoded, global::HRV);
+        assert_eq!(hrv.len(), 1);
+        assert_eq!(
+            hrv[0].field(0).unwrap().as_u32_array(),
+            [1000, 500, 0xFFFF, 0xFFFF, 0xFFFF]
+        );
+
+        let lap = messages(&decoded, global::LAP);
+        assert_eq!(lap.len(), 1);
+        assert_eq!(lap[0].timestamp(), Some(START + 2));
+        assert_eq!(value(lap[0], 2), Some(START));
+        assert_eq!(value(lap[0], 7), Some(2000));
+        assert_eq!(value(lap[0], 8), Some(2000));
+        // 8 kJ
+        assert_eq!(value(lap[0], 11), Some(1));
+        assert_eq!(value(lap[0], 15), Some(100));
+        assert_eq!(value(lap[0], 16), Some(120));
+
+        let session = messages(&decoded, global::SESSION);
+        assert_eq!(session.len(), 1);
+        assert_eq!(session[0].timestamp(), Some(START + 2));
+        assert_eq!(value(session[0], 2), Some(START));
+        assert_eq!(value(session[0], 7), Some(2000));
+        assert_eq!(value(session[0], 11), Some(1));
+        assert_eq!(value(session[0], 16), Some(100));
+        assert_eq!(value(session[0], 17), Some(120));
+        assert_eq!(value(session[0], 26), Some(1));
+
+        assert_eq!(decoded.first().map(|v| v.global), Some(global::FILE_ID));
+        assert_eq!(decoded.last().map(|v| v.global), Some(global::ACTIVITY));
+    }
+
+    #[test]
+    fn crc_covers_the_file() {
+        let mut file = encode(&test_session(&[80, 90])).unwrap();
+        let crc = u16::from_le_bytes([file[file.len() - 2], file[file.len() - 1]]);
+        assert_ne!(crc, 0);
+
+        let middle = file.len() / 2;
+        file[middle] ^= 0xFF;
+        assert_eq!(fit::decode(&file), Err(FitError::CrcMismatch));
+    }
+
+    #[test]
+    fn invalid_heart_rate_without_samples() {
+        let decoded = fit::decode(&encode(&test_session(&[0])).unwrap()).unwrap();
+        let lap = messages(&decoded, global::LAP);
+        assert_eq!(value(lap[0], 15), Some(0xFF));
+        assert_eq!(value(lap[0], 16), Some(0xFF));
+    }
+}
@@ -0,0 +1,257 @@
+//! GPX 1.1 with Garmin TrackPointExtension v1, see <https://www.topografix.com/GPX/1/1/gpx.xsd>
+//! and <https://www8.garmin.com/xmlschemas/TrackPointExtensionv1.xsd>
+
+use std::fmt::Write;
+
+use super::{escape_xml, heart_rate_bpm};
+use crate::datetime::UtcDateTime;
+use crate::session::Session;
+
+/// A single track segment, `None` if the session has no samples. The track points have time and
+/// heart rate only: the schema requires coordinates, but a session has none, and a made up
+/// position would be plotted as if it was real.
+pub fn encode(session: &Session, name: &str) -> Option<String> {
+    let first = session.samples.first()?;
+    let mut xml = String::new();
+    xml.push_str(r#"<?xml version="1.0" encoding="UTF-8"?>"#);
+    xml.push('\n');
+    xml.push_str(concat!(
+        r#"<gpx version="1.1" creator="hr-view" "#,
+        r#"xmlns="http://www.topografix.com/GPX/1/1" "#,
+        r#"xmlns:gpxtpx="http://www.garmin.com/xmlschemas/TrackPointExtension/v1" "#,
+        r#"xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance" "#,
+        r#"xsi:schemaLocation="http://www.topografix.com/GPX/1/1 "#,
+        r#"http://www.topografix.com/GPX/1/1/gpx.xsd "#,
+        r#"http://www.garmin.com/xmlschemas/TrackPointExtension/v1 "#,
+        r#"http://www.garmin.com/xmlschemas/TrackPointExtensionv1.xsd">"#,
+        "\n"
+    ));
+    let _ = writeln!(
+        xml,
+        "  <metadata><time>{}</time></metadata>",
+        UtcDateTime::from(first.wall_clock)
+    );
+    xml.push_str("  <trk>\n");
+    let _ = writeln!(xml, "    <name>{}</name>", escape_xml(name));
+    xml.push_str("    <trkseg>\n");
+    for sample in &session.samples {
+        xml.push_str("      <trkpt>\n");
+        let _ = writeln!(
+            xml,
+            "        <time>{}</time>",
+            UtcDateTime::from(sample.wall_clock)
+        );
+        if let Some(hr) = heart_rate_bpm(&sample.hrm) {
+            xml.push_str("        <extensions>\n");
+            xml.push_str("          <gpxtpx:TrackPointExtension>\n");
+            let _ = writeln!(xml, "            <gpxtpx:hr>{hr}</gpxtpx:hr>");
+            xml.push_str("          </gpxtpx:TrackPointExtension>\n");
+            xml.push_str("        </extensions>\n");
+        }
+        xml.push_str("      </trkpt>\n");
+    }
+    xml.push_str("    </trkseg>\n");
+    xml.push_str("  </trk>\n");
+    xml.push_str("</gpx>\n");
+    Some(xml)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::export::schema::{self, Attribute, Complex, Element, Particle, Simple, Type};
+    use crate::export::test_session;
+
+    const NAMESPACE: &str = "http://www.topografix.com/GPX/1/1";
+    const TPX_NAMESPACE: &str = "http://www.garmin.com/xmlschemas/TrackPointExtension/v1";
+
+    /// The types of gpx.xsd and TrackPointExtensionv1.xsd used by the export
+    mod xsd {
+        use super::*;
+
+        const fn complex(
+            namespace: &'static str,
+            attributes: &'static [Attribute],
+            sequence: &'static [Particle],
+        ) -> Complex {
+            Complex {
+                namespace,
+                xsi_type: None,
+                attributes,
+                sequence,
+                any_other: None,
+            }
+        }
+
+        const DOUBLE: Type = Type::Simple(Simple::Double);
+        const STRING: Type = Type::Simple(Simple::String);
+        const DATE_TIME: Type = Type::Simple(Simple::DateTime);
+        const TPX_EXTENSIONS: Complex = Complex {
+            any_other: Some(&[]),
+            ..complex(TPX_NAMESPACE, &[], &[])
+        };
+        const TRACK_POINT_EXTENSION: Complex = complex(
+            TPX_NAMESPACE,
+            &[],
+            &[
+                Particle::optional("atemp", DOUBLE),
+                Particle::optional("wtemp", DOUBLE),
+                Particle::optional("depth", DOUBLE),
+                Particle::optional("hr", Type::Simple(Simple::UnsignedByte(1, 255))),
+                Particle::optional("cad", Type::Simple(Simple::UnsignedByte(0, 254))),
+                Particle::optional("Extensions", Type::Complex(&TPX_EXTENSIONS)),
+            ],
+        );
+        const EXTENSIONS: Complex = Complex {
+            any_other: Some(&[Element {
+                namespace: TPX_NAMESPACE,
+                name: "TrackPointExtension",
+                ty: Type::Complex(&TRACK_POINT_EXTENSION),
+            }]),
+            ..complex(NAMESPACE, &[], &[])
+        };
+        const COORDINATE: Simple = Simple::Double;
+        const WAYPOINT: Complex = complex(
+            NAMESPACE,
+            &[
+                Attribute {
+                    name: "lat",
+                    required: true,
+                    ty: COORDINATE,
+                },
+                Attribute {
+                    name: "lon",
+                    required: true,
+                    ty: COORDINATE,
+                },
+            ],
+            &[
+                Particle::optional("ele", DOUBLE),
+                Particle::optional("time", DATE_TIME),
+                Particle::optional("magvar", DOUBLE),
+                Particle::optional("geoidheight", DOUBLE),
+                Particle::optional("name", STRING),
+                Particle::optional("cmt", STRING),
+                Particle::optional("desc", STRING),
+                Particle::optional("src", STRING),
+                Particle::optional("sym", STRING),
+                Particle::optional("type", STRING),
+                Particle::optional("extensions", Type::Complex(&EXTENSIONS)),
+            ],
+        );
+        const SEGMENT: Complex = complex(
+            NAMESPACE,
+            &[],
+            &[
+                Particle::new("trkpt", 0, None, Type::Complex(&WAYPOINT)),
+                Particle::optional("extensions", Type::Complex(&EXTENSIONS)),
+            ],
+        );
+        const TRACK: Complex = complex(
+            NAMESPACE,
+            &[],
+            &[
+                Particle::optional("name", STRING),
+                Particle::optional("cmt", STRING),
+                Particle::optional("desc", STRING),
+                Particle::optional("src", STRING),
+                Particle::optional("number", Type::Simple(Simple::UnsignedInt)),
+                Particle::optional("type", STRING),
+                Particle::optional("extensions", Type::Complex(&EXTENSIONS)),
+                Particle::new("trkseg", 0, None, Type::Complex(&SEGMENT)),
+            ],
+        );
+        const METADATA: Complex = complex(
+            NAMESPACE,
+            &[],
+            &[
+                Particle::optional("name", STRING),
+                Particle::optional("desc", STRING),
+                Particle::optional("time", DATE_TIME),
+                Particle::optional("keywords", STRING),
+                Particle::optional("extensions", Type::Complex(&EXTENSIONS)),
+            ],
+        );
+        pub const ROOT: Element = Element {
+            namespace: NAMESPACE,
+            name: "gpx",
+            ty: Type::Complex(&complex(
+                NAMESPACE,
+                &[
+                    Attribute {
+                        name: "version",
+                        required: true,
+                        ty: Simple::Fixed("1.1"),
+                    },
+                    Attribute {
+                        name: "creator",
+                        required: true,
+                        ty: Simple::String,
+                    },
+                ],
+                &[
+                    Particle::optional("metadata", Type::Complex(&METADATA)),
+                    Particle::new("trk", 0, None, Type::Complex(&TRACK)),
+                    Particle::optional("extensions", Type::Complex(&EXTENSIONS)),
+                ],
+            )),
+        };
+    }
+
+    /// Violations of the schema except the coordinates, which a session doesn't have
+    fn validate(xml: &str) -> Vec<String> {
+        let mut errors = schema::validate(xml, &xsd::ROOT);
+        errors.retain(|v| {
+            !v.ends_with("missing attribute lat") && !v.ends_with("missing attribute lon")
+        });
+        errors
+    }
+
+    #[test]
+    fn conforms_to_schema_without_coordinates() {
+        for heart_rates in [&[80, 90][..], &[0], &[0, 300, 120], &[255]] {
+            let xml = encode(&test_session(heart_rates), "<name> & \"notes\"").unwrap();
+            assert_eq!(validate(&xml), Vec::<String>::new());
+            // Only the coordinates are missing
+            let errors = schema::validate(&xml, &xsd::ROOT);
+            assert_eq!(errors.len(), heart_rates.len() * 2);
+            assert!(!xml.contains("lat=") && !xml.contains("lon="));
+        }
+    }
+
+    #[test]
+    fn schema_rejects_invalid_documents() {
+        let xml = encode(&test_session(&[80, 90]), "name").unwrap();
+        let invalid = [
+            xml.replace("<gpxtpx:hr>80</gpxtpx:hr>", "<gpxtpx:hr>0</gpxtpx:hr>"),
+            xml.replace(r#"version="1.1""#, r#"version="1.0""#),
+            xml.replacen("<time>", "<time>x", 2),
+            xml.replace("<trk>", "<trk><trkseg/>")
+                .replace("</trk>", "<name>late</name></trk>"),
+        ];
+        for xml in invalid {
+            assert!(!validate(&xml).is_empty(), "{xml}");
+        }
+    }
+
+    #[test]
+    fn track_points() {
+        let xml = encode(&test_session(&[80, 0, 120]), "a & b").unwrap();
+        assert!(xml.contains("<metadata><time>2025-01-02T03:04:05.000Z</time></metadata>"));
+        assert!(xml.contains("<name>a &amp; b</name>"));
+
+        let points = xml.split("<trkpt>").skip(1).collect::<Vec<_>>();
+        assert_eq!(points.len(), 3);
+        assert!(points[0].contains("<time>2025-01-02T03:04:05.000Z</time>"));
+        assert!(points[0].contains("<gpxtpx:hr>80</gpxtpx:hr>"));
+        // No heart rate without contact
+        assert!(points[1].contains("<time>2025-01-02T03:04:06.000Z</time>"));
+        assert!(!points[1].contains("<extensions>"));
+        assert!(points[2].contains("<gpxtpx:hr>120</gpxtpx:hr>"));
+    }
+
+    #[test]
+    fn reject_empty_session() {
+        assert_eq!(encode(&test_session(&[]), "name"), None);
+    }
+}
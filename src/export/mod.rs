@@ -0,0 +1,165 @@
+//! Export recorded sessions to formats accepted by training platforms
+
+mod fit;
+mod gpx;
+#[cfg(test)]
+mod schema;
+mod tcx;
+
+use std::fmt::Display;
+use std::path::{Path, PathBuf};
+use std::{fs, io};
+
+use crate::hrm::HeartRateMeasurement;
+use crate::session::Session;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Tcx,
+    Gpx,
+    Fit,
+}
+
+/// Heart rate summary of a session, for the lap and session summaries of exported files
+struct Summary {
+    average_heart_rate: Option<u8>,
+    max_heart_rate: Option<u8>,
+    /// Unit: kcal
+    calories: u16,
+}
+
+impl Format {
+    pub const ALL: &[Self] = &[Self::Tcx, Self::Gpx, Self::Fit];
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Tcx => "tcx",
+            Self::Gpx => "gpx",
+            Self::Fit => "fit",
+        }
+    }
+
+    /// Encode the session, `name` is used as the activity or track name where supported. A
+    /// session without samples can't be encoded.
+    pub fn encode(&self, session: &Session, name: &str) -> io::Result<Vec<u8>> {
+        let empty = || io::Error::new(io::ErrorKind::InvalidInput, "the session has no samples");
+        match self {
+            Self::Tcx => tcx::encode(session, name)
+                .map(String::into_bytes)
+                .ok_or_else(empty),
+            Self::Gpx => gpx::encode(session, name)
+                .map(String::into_bytes)
+                .ok_or_else(empty),
+            Self::Fit if session.samples.is_empty() => Err(empty()),
+            Self::Fit => {
+                fit::encode(session).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+            }
+        }
+    }
+}
+
+impl Display for Format {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Tcx => "TCX",
+            Self::Gpx => "GPX",
+            Self::Fit => "FIT",
+        })
+    }
+}
+
+impl Summary {
+    /// kJ per kcal
+    const KJ_PER_KCAL: f32 = 4.184;
+
+    fn of(session: &Session) -> Self {
+        let heart_rates = session
+            .samples
+            .iter()
+            .filter_map(|v| heart_rate_bpm(&v.hrm))
+            .collect::<Vec<_>>();
+        let energy = session
+            .samples
+            .iter()
+            .filter_map(|v| v.hrm.energy_expended)
+            .collect::<Vec<_>>();
+        let calories = match (energy.first(), energy.last()) {
+            (Some(first), Some(last)) => {
+                (last.saturating_sub(*first) as f32 / Self::KJ_PER_KCAL) as u16
+            }
+            _ => 0,
+        };
+        Self {
+            average_heart_rate: (!heart_rates.is_empty()).then(|| {
+                (heart_rates.iter().map(|v| *v as u32).sum::<u32>() / heart_rates.len() as u32)
+                    as u8
+            }),
+            max_heart_rate: heart_rates.iter().max().copied(),
+            calories,
+        }
+    }
+}
+
+/// Export the session file to `format`, next to it with the format's extension. Return the path
+/// of exported file.
+pub fn export_file(path: &Path, format: Format) -> io::Result<PathBuf> {
+    let session = Session::load(path)?;
+    let name = path
+        .file_stem()
+        .map_or_else(String::new, |v| v.to_string_lossy().into_owned());
+    let output = path.with_extension(format.extension());
+    fs::write(&output, format.encode(&session, &name)?)?;
+    Ok(output)
+}
+
+/// Heart rate in the valid range of XML schemas (`1..=255`), `None` if it is out of range or the
+/// sensor contact is lost.
+fn heart_rate_bpm(hrm: &HeartRateMeasurement) -> Option<u8> {
+    if hrm.contact_lost() {
+        return None;
+    }
+    u8::try_from(hrm.heart_rate).ok().filter(|v| *v > 0)
+}
+
+/// Escape text for XML content and attribute values
+fn escape_xml(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// A session of `heart_rates` one second apart, the first one at 2025-01-02 03:04:05 UTC. A
+/// heart rate of 0 is recorded with the sensor contact lost.
+#[cfg(test)]
+fn test_session(heart_rates: &[u16]) -> Session {
+    use std::time::{Duration, SystemTime};
+
+    use crate::session::Sample;
+
+    let start = SystemTime::UNIX_EPOCH + Duration::from_secs(1_735_787_045);
+    Session {
+        samples: heart_rates
+            .iter()
+            .enumerate()
+            .map(|(i, hr)| Sample {
+                wall_clock: start + Duration::from_secs(i as u64),
+                monotonic: Duration::from_secs(i as u64),
+                hrm: HeartRateMeasurement {
+                    heart_rate: *hr,
+                    sensor_contact: Some(*hr != 0),
+                    energy_expended: Some(100 + i as u16 * 4),
+                    rr_intervals: Vec::new(),
+                },
+            })
+            .collect(),
+    }
+}
@@ -0,0 +1,203 @@
+//! Validate exported XML against the subset of XML Schema used by the published TCX and GPX
+//! schemas: sequences of elements with occurrence bounds, attributes, simple types, `xsi:type` of
+//! abstract types and `<xsd:any namespace="##other" processContents="lax"/>`.
+
+use roxmltree::{Document, Node};
+
+use crate::datetime::UtcDateTime;
+
+const XSI_NAMESPACE: &str = "http://www.w3.org/2001/XMLSchema-instance";
+
+/// A global element declaration
+#[derive(Clone, Copy)]
+pub struct Element {
+    pub namespace: &'static str,
+    pub name: &'static str,
+    pub ty: Type,
+}
+
+#[derive(Clone, Copy)]
+pub enum Type {
+    Simple(Simple),
+    Complex(&'static Complex),
+}
+
+#[derive(Clone, Copy)]
+pub enum Simple {
+    String,
+    DateTime,
+    Double,
+    UnsignedShort,
+    UnsignedInt,
+    /// `xsd:unsignedByte` restricted to the range
+    UnsignedByte(u8, u8),
+    Enumeration(&'static [&'static str]),
+    Fixed(&'static str),
+}
+
+pub struct Complex {
+    /// Namespace of child elements
+    pub namespace: &'static str,
+    /// Required `xsi:type` for an abstract type
+    pub xsi_type: Option<&'static str>,
+    pub attributes: &'static [Attribute],
+    /// A sequence of child elements
+    pub sequence: &'static [Particle],
+    /// Known elements of `##other` namespace, elements of other namespaces are not validated
+    pub any_other: Option<&'static [Element]>,
+}
+
+pub struct Attribute {
+    pub name: &'static str,
+    pub required: bool,
+    pub ty: Simple,
+}
+
+pub struct Particle {
+    pub name: &'static str,
+    pub min: usize,
+    /// `None` for unbounded
+    pub max: Option<usize>,
+    pub ty: Type,
+}
+
+impl Particle {
+    pub const fn one(name: &'static str, ty: Type) -> Self {
+        Self::new(name, 1, Some(1), ty)
+    }
+
+    pub const fn optional(name: &'static str, ty: Type) -> Self {
+        Self::new(name, 0, Some(1), ty)
+    }
+
+    pub const fn new(name: &'static str, min: usize, max: Option<usize>, ty: Type) -> Self {
+        Self { name, min, max, ty }
+    }
+}
+
+/// Validate `xml` against `root`, return all violations
+pub fn validate(xml: &str, root: &Element) -> Vec<String> {
+    let document = match Document::parse(xml) {
+        Ok(v) => v,
+        Err(e) => return vec![format!("not well-formed: {e}")],
+    };
+    let mut errors = Vec::new();
+    let element = document.root_element();
+    let name = element.tag_name();
+    if name.namespace() != Some(root.namespace) || name.name() != root.name {
+        errors.push(format!("unexpected root element {name:?}"));
+    } else {
+        validate_element(element, root.ty, &mut errors);
+    }
+    errors
+}
+
+fn validate_element(node: Node, ty: Type, errors: &mut Vec<String>) {
+    let path = path(node);
+    match ty {
+        Type::Simple(simple) => {
+            if node.children().any(|v| v.is_element()) {
+                errors.push(format!("{path}: elements in simple content"));
+            }
+            let text = node.text().unwrap_or_default();
+            if !simple.accepts(text) {
+                errors.push(format!("{path}: invalid value {text:?}"));
+            }
+        }
+        Type::Complex(complex) => validate_complex(node, complex, &path, errors),
+    }
+}
+
+fn validate_complex(node: Node, complex: &Complex, path: &str, errors: &mut Vec<String>) {
+    if let Some(expected) = complex.xsi_type {
+        let ty = node.attribute((XSI_NAMESPACE, "type"));
+        if ty != Some(expected) {
+            errors.push(format!("{path}: xsi:type {ty:?} is not {expected:?}"));
+        }
+    }
+    for attribute in node.attributes().filter(|v| v.namespace().is_none()) {
+        match complex
+            .attributes
+            .iter()
+            .find(|v| v.name == attribute.name())
+        {
+            Some(v) if !v.ty.accepts(attribute.value()) => errors.push(format!(
+                "{path}: invalid attribute {}={:?}",
+                attribute.name(),
+                attribute.value()
+            )),
+            Some(_) => {}
+            None => errors.push(format!("{path}: unexpected attribute {}", attribute.name())),
+        }
+    }
+    for attribute in complex.attributes.iter().filter(|v| v.required) {
+        if node.attribute(attribute.name).is_none() {
+            errors.push(format!("{path}: missing attribute {}", attribute.name));
+        }
+    }
+    if node
+        .children()
+        .any(|v| v.is_text() && !v.text().unwrap_or_default().trim().is_empty())
+    {
+        errors.push(format!("{path}: text in element-only content"));
+    }
+
+    let mut children = node.children().filter(|v| v.is_element()).peekable();
+    for particle in complex.sequence {
+        let mut count = 0;
+        while let Some(child) = children.next_if(|v| {
+            v.tag_name().namespace() == Some(complex.namespace)
+                && v.tag_name().name() == particle.name
+        }) {
+            validate_element(child, particle.ty, errors);
+            count += 1;
+        }
+        if count < particle.min || particle.max.is_some_and(|max| count > max) {
+            errors.push(format!("{path}: {count} <{}> elements", particle.name));
+        }
+    }
+    for child in children {
+        let name = child.tag_name();
+        let other = name.namespace().is_some_and(|v| v != complex.namespace);
+        match complex.any_other {
+            Some(known) if other => {
+                if let Some(element) = known
+                    .iter()
+                    .find(|v| name.namespace() == Some(v.namespace) && name.name() == v.name)
+                {
+                    validate_element(child, element.ty, errors);
+                }
+            }
+            _ => errors.push(format!("{path}: unexpected element <{}>", name.name())),
+        }
+    }
+}
+
+impl Simple {
+    fn accepts(&self, text: &str) -> bool {
+        let text = text.trim();
+        match self {
+            Self::String => true,
+            // `UtcDateTime::parse` is lenient with the separator
+            Self::DateTime => text.get(10..11) == Some("T") && UtcDateTime::parse(text).is_some(),
+            Self::Double => text.parse::<f64>().is_ok_and(f64::is_finite),
+            Self::UnsignedShort => text.parse::<u16>().is_ok(),
+            Self::UnsignedInt => text.parse::<u32>().is_ok(),
+            Self::UnsignedByte(min, max) => {
+                text.parse::<u8>().is_ok_and(|v| (*min..=*max).contains(&v))
+            }
+            Self::Enumeration(values) => values.contains(&text),
+            Self::Fixed(value) => text == *value,
+        }
+    }
+}
+
+fn path(node: Node) -> String {
+    let mut names = node
+        .ancestors()
+        .filter(|v| v.is_element())
+        .map(|v| v.tag_name().name())
+        .collect::<Vec<_>>();
+    names.reverse();
+    names.join("/")
+}
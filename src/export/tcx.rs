@@ -0,0 +1,298 @@
+//! Garmin Training Center Database v2, see
+//! <https://www8.garmin.com/xmlschemas/TrainingCenterDatabasev2.xsd>
+
+use std::fmt::Write;
+
+use super::{Summary, escape_xml, heart_rate_bpm};
+use crate::datetime::UtcDateTime;
+use crate::session::Session;
+
+/// A single `Other` activity with one lap, the track points have time and heart rate only. `None`
+/// if the session has no samples.
+pub fn encode(session: &Session, name: &str) -> Option<String> {
+    let start = UtcDateTime::from(session.samples.first()?.wall_clock);
+    let summary = Summary::of(session);
+
+    let mut xml = String::new();
+    xml.push_str(r#"<?xml version="1.0" encoding="UTF-8"?>"#);
+    xml.push('\n');
+    xml.push_str(concat!(
+        r#"<TrainingCenterDatabase xmlns="http://www.garmin.com/xmlschemas/TrainingCenterDatabase/v2" "#,
+        r#"xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance" "#,
+        r#"xsi:schemaLocation="http://www.garmin.com/xmlschemas/TrainingCenterDatabase/v2 "#,
+        r#"http://www.garmin.com/xmlschemas/TrainingCenterDatabasev2.xsd">"#,
+        "\n"
+    ));
+    xml.push_str("  <Activities>\n");
+    xml.push_str("    <Activity Sport=\"Other\">\n");
+    let _ = writeln!(xml, "      <Id>{start}</Id>");
+    let _ = writeln!(xml, "      <Lap StartTime=\"{start}\">");
+    let _ = writeln!(
+        xml,
+        "        <TotalTimeSeconds>{:.3}</TotalTimeSeconds>",
+        session.duration().as_secs_f64()
+    );
+    xml.push_str("        <DistanceMeters>0</DistanceMeters>\n");
+    let _ = writeln!(xml, "        <Calories>{}</Calories>", summary.calories);
+    if let (Some(average), Some(maximum)) = (summary.average_heart_rate, summary.max_heart_rate) {
+        let _ = writeln!(
+            xml,
+            "        <AverageHeartRateBpm><Value>{average}</Value></AverageHeartRateBpm>"
+        );
+        let _ = writeln!(
+            xml,
+            "        <MaximumHeartRateBpm><Value>{maximum}</Value></MaximumHeartRateBpm>"
+        );
+    }
+    xml.push_str("        <Intensity>Active</Intensity>\n");
+    xml.push_str("        <TriggerMethod>Manual</TriggerMethod>\n");
+    xml.push_str("        <Track>\n");
+    for sample in &session.samples {
+        xml.push_str("          <Trackpoint>\n");
+        let _ = writeln!(
+            xml,
+            "            <Time>{}</Time>",
+            UtcDateTime::from(sample.wall_clock)
+        );
+        if let Some(hr) = heart_rate_bpm(&sample.hrm) {
+            let _ = writeln!(
+                xml,
+                "            <HeartRateBpm><Value>{hr}</Value></HeartRateBpm>"
+            );
+        }
+        if let Some(contact) = sample.hrm.sensor_contact {
+            let state = if contact { "Present" } else { "Absent" };
+            let _ = writeln!(xml, "            <SensorState>{state}</SensorState>");
+        }
+        xml.push_str("          </Trackpoint>\n");
+    }
+    xml.push_str("        </Track>\n");
+    let _ = writeln!(xml, "        <Notes>{}</Notes>", escape_xml(name));
+    xml.push_str("      </Lap>\n");
+    xml.push_str("      <Creator xsi:type=\"Device_t\">\n");
+    xml.push_str("        <Name>hr-view</Name>\n");
+    xml.push_str("        <UnitId>0</UnitId>\n");
+    xml.push_str("        <ProductID>0</ProductID>\n");
+    let _ = writeln!(
+        xml,
+        "        <Version><VersionMajor>{}</VersionMajor><VersionMinor>{}</VersionMinor></Version>",
+        env!("CARGO_PKG_VERSION_MAJOR"),
+        env!("CARGO_PKG_VERSION_MINOR")
+    );
+    xml.push_str("      </Creator>\n");
+    xml.push_str("    </Activity>\n");
+    xml.push_str("  </Activities>\n");
+    xml.push_str("</TrainingCenterDatabase>\n");
+    Some(xml)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::export::schema::{self, Attribute, Complex, Element, Particle, Simple, Type};
+    use crate::export::test_session;
+
+    const NAMESPACE: &str = "http://www.garmin.com/xmlschemas/TrainingCenterDatabase/v2";
+
+    /// The types of TrainingCenterDatabasev2.xsd used by the export
+    mod xsd {
+        use super::*;
+
+        const fn complex(
+            attributes: &'static [Attribute],
+            sequence: &'static [Particle],
+        ) -> Complex {
+            Complex {
+                namespace: NAMESPACE,
+                xsi_type: None,
+                attributes,
+                sequence,
+                any_other: None,
+            }
+        }
+
+        const DOUBLE: Type = Type::Simple(Simple::Double);
+        const DATE_TIME: Type = Type::Simple(Simple::DateTime);
+        const UNSIGNED_SHORT: Type = Type::Simple(Simple::UnsignedShort);
+        const EXTENSIONS: Complex = Complex {
+            any_other: Some(&[]),
+            ..complex(&[], &[])
+        };
+        const HEART_RATE: Complex = complex(
+            &[],
+            &[Particle::one(
+                "Value",
+                Type::Simple(Simple::UnsignedByte(1, 255)),
+            )],
+        );
+        const POSITION: Complex = complex(
+            &[],
+            &[
+                Particle::one("LatitudeDegrees", DOUBLE),
+                Particle::one("LongitudeDegrees", DOUBLE),
+            ],
+        );
+        const TRACKPOINT: Complex = complex(
+            &[],
+            &[
+                Particle::one("Time", DATE_TIME),
+                Particle::optional("Position", Type::Complex(&POSITION)),
+                Particle::optional("AltitudeMeters", DOUBLE),
+                Particle::optional("DistanceMeters", DOUBLE),
+                Particle::optional("HeartRateBpm", Type::Complex(&HEART_RATE)),
+                Particle::optional("Cadence", Type::Simple(Simple::UnsignedByte(0, 254))),
+                Particle::optional(
+                    "SensorState",
+                    Type::Simple(Simple::Enumeration(&["Present", "Absent"])),
+                ),
+                Particle::optional("Extensions", Type::Complex(&EXTENSIONS)),
+            ],
+        );
+        const TRACK: Complex = complex(
+            &[],
+            &[Particle::new(
+                "Trackpoint",
+                1,
+                None,
+                Type::Complex(&TRACKPOINT),
+            )],
+        );
+        const LAP: Complex = complex(
+            &[Attribute {
+                name: "StartTime",
+                required: true,
+                ty: Simple::DateTime,
+            }],
+            &[
+                Particle::one("TotalTimeSeconds", DOUBLE),
+                Particle::one("DistanceMeters", DOUBLE),
+                Particle::optional("MaximumSpeed", DOUBLE),
+                Particle::one("Calories", UNSIGNED_SHORT),
+                Particle::optional("AverageHeartRateBpm", Type::Complex(&HEART_RATE)),
+                Particle::optional("MaximumHeartRateBpm", Type::Complex(&HEART_RATE)),
+                Particle::one(
+                    "Intensity",
+                    Type::Simple(Simple::Enumeration(&["Active", "Resting"])),
+                ),
+                Particle::optional("Cadence", Type::Simple(Simple::UnsignedByte(0, 254))),
+                Particle::one(
+                    "TriggerMethod",
+                    Type::Simple(Simple::Enumeration(&[
+                        "Manual",
+                        "Distance",
+                        "Location",
+                        "Time",
+                        "HeartRate",
+                    ])),
+                ),
+                Particle::new("Track", 0, None, Type::Complex(&TRACK)),
+                Particle::optional("Notes", Type::Simple(Simple::String)),
+                Particle::optional("Extensions", Type::Complex(&EXTENSIONS)),
+            ],
+        );
+        const VERSION: Complex = complex(
+            &[],
+            &[
+                Particle::one("VersionMajor", UNSIGNED_SHORT),
+                Particle::one("VersionMinor", UNSIGNED_SHORT),
+                Particle::optional("BuildMajor", UNSIGNED_SHORT),
+                Particle::optional("BuildMinor", UNSIGNED_SHORT),
+            ],
+        );
+        const DEVICE: Complex = Complex {
+            xsi_type: Some("Device_t"),
+            ..complex(
+                &[],
+                &[
+                    Particle::one("Name", Type::Simple(Simple::String)),
+                    Particle::one("UnitId", Type::Simple(Simple::UnsignedInt)),
+                    Particle::one("ProductID", UNSIGNED_SHORT),
+                    Particle::one("Version", Type::Complex(&VERSION)),
+                ],
+            )
+        };
+        const ACTIVITY: Complex = complex(
+            &[Attribute {
+                name: "Sport",
+                required: true,
+                ty: Simple::Enumeration(&["Running", "Biking", "Other"]),
+            }],
+            &[
+                Particle::one("Id", DATE_TIME),
+                Particle::new("Lap", 1, None, Type::Complex(&LAP)),
+                Particle::optional("Notes", Type::Simple(Simple::String)),
+                Particle::optional("Creator", Type::Complex(&DEVICE)),
+                Particle::optional("Extensions", Type::Complex(&EXTENSIONS)),
+            ],
+        );
+        const ACTIVITIES: Complex = complex(
+            &[],
+            &[Particle::new("Activity", 0, None, Type::Complex(&ACTIVITY))],
+        );
+        pub const ROOT: Element = Element {
+            namespace: NAMESPACE,
+            name: "TrainingCenterDatabase",
+            ty: Type::Complex(&complex(
+                &[],
+                &[
+                    Particle::optional("Activities", Type::Complex(&ACTIVITIES)),
+                    Particle::optional("Extensions", Type::Complex(&EXTENSIONS)),
+                ],
+            )),
+        };
+    }
+
+    #[test]
+    fn conforms_to_schema() {
+        for heart_rates in [&[80, 90][..], &[0], &[0, 300, 120], &[255]] {
+            let xml = encode(&test_session(heart_rates), "<name> & \"notes\"").unwrap();
+            assert_eq!(schema::validate(&xml, &xsd::ROOT), Vec::<String>::new());
+        }
+    }
+
+    #[test]
+    fn schema_rejects_invalid_documents() {
+        let xml = encode(&test_session(&[80, 90]), "name").unwrap();
+        let invalid = [
+            xml.replace("<Calories>", "<Calories>-"),
+            xml.replace("<Intensity>Active</Intensity>", ""),
+            xml.replace("<Value>80</Value>", "<Value>0</Value>"),
+            xml.replace(r#"xsi:type="Device_t""#, ""),
+            xml.replacen("<Time>", "<Time>2025-01-02 ", 1),
+            // Out of order
+            xml.replace("<Intensity>Active</Intensity>", "").replace(
+                "<TriggerMethod>Manual</TriggerMethod>",
+                "<TriggerMethod>Manual</TriggerMethod><Intensity>Active</Intensity>",
+            ),
+        ];
+        for xml in invalid {
+            assert!(!schema::validate(&xml, &xsd::ROOT).is_empty(), "{xml}");
+        }
+    }
+
+    #[test]
+    fn heart_rate_values() {
+        let xml = encode(&test_session(&[80, 0, 120]), "a & b").unwrap();
+        assert!(xml.contains(r#"<Id>2025-01-02T03:04:05.000Z</Id>"#));
+        assert!(xml.contains("<TotalTimeSeconds>2.000</TotalTimeSeconds>"));
+        assert!(xml.contains("<AverageHeartRateBpm><Value>100</Value></AverageHeartRateBpm>"));
+        assert!(xml.contains("<MaximumHeartRateBpm><Value>120</Value></MaximumHeartRateBpm>"));
+        assert!(xml.contains("<Notes>a &amp; b</Notes>"));
+
+        let trackpoints = xml.split("<Trackpoint>").skip(1).collect::<Vec<_>>();
+        assert_eq!(trackpoints.len(), 3);
+        assert!(trackpoints[0].contains("<Time>2025-01-02T03:04:05.000Z</Time>"));
+        assert!(trackpoints[0].contains("<HeartRateBpm><Value>80</Value></HeartRateBpm>"));
+        assert!(trackpoints[0].contains("<SensorState>Present</SensorState>"));
+        // No heart rate without contact
+        assert!(!trackpoints[1].contains("HeartRateBpm"));
+        assert!(trackpoints[1].contains("<SensorState>Absent</SensorState>"));
+        assert!(trackpoints[2].contains("<Time>2025-01-02T03:04:07.000Z</Time>"));
+        assert!(trackpoints[2].contains("<HeartRateBpm><Value>120</Value></HeartRateBpm>"));
+    }
+
+    #[test]
+    fn reject_empty_session() {
+        assert_eq!(encode(&test_session(&[]), "name"), None);
+    }
+}
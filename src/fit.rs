@@ -0,0 +1,482 @@
+//! Minimal codec of Garmin _Flexible and Interoperable Data Transfer_ (FIT) files, see
+//! <https://developer.garmin.com/fit/protocol/>
+//!
+//! Messages are kept as raw fields, so files written by other software can be decoded, modified
+//! and encoded again without knowing their profile. Only the first FIT file of a chained file is
+//! decoded.
+
+use std::fmt::Display;
+
+/// Seconds between UNIX epoch and FIT epoch (1989-12-31T00:00:00Z)
+pub const FIT_EPOCH_OFFSET: u64 = 631_065_600;
+/// Field number of `timestamp`, common to all messages
+pub const TIMESTAMP_FIELD: u8 = 253;
+
+const HEADER_SIZE: u8 = 14;
+const PROTOCOL_VERSION: u8 = 0x20;
+const PROFILE_VERSION: u16 = 2132;
+const DEFINITION_FLAG: u8 = 0x40;
+const DEVELOPER_DATA_FLAG: u8 = 0x20;
+const COMPRESSED_TIMESTAMP_FLAG: u8 = 0x80;
+const LOCAL_MESSAGE_TYPES: usize = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct BaseType(pub u8);
+
+/// A field of a message. Regardless of the architecture of the message, `data` is little endian.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Field {
+    pub number: u8,
+    pub base_type: BaseType,
+    pub data: Vec<u8>,
+}
+
+/// A field defined by a developer, the data is kept as is because its type is unknown here.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DeveloperField {
+    pub number: u8,
+    pub developer_index: u8,
+    pub data: Vec<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Message {
+    pub global: u16,
+    /// Architecture of the message, only affects how `developer_fields` are interpreted.
+    pub big_endian: bool,
+    pub fields: Vec<Field>,
+    pub developer_fields: Vec<DeveloperField>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FitError {
+    InvalidHeader,
+    Truncated,
+    CrcMismatch,
+    UndefinedLocalMessage(u8),
+    /// A field of the global message type is longer than 255 bytes
+    FieldTooLong(u16),
+    /// A message of the global message type has more than 255 fields
+    TooManyFields(u16),
+}
+
+/// Layout of a message in file, messages with the same layout share a local message type.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Definition {
+    global: u16,
+    big_endian: bool,
+    /// Number, size and base type
+    fields: Vec<(u8, u8, BaseType)>,
+    /// Number, size and developer data index
+    developer_fields: Vec<(u8, u8, u8)>,
+}
+
+impl BaseType {
+    pub const ENUM: Self = Self(0x00);
+    pub const UINT8: Self = Self(0x02);
+    pub const UINT16: Self = Self(0x84);
+    pub const UINT32: Self = Self(0x86);
+
+    /// Size of a single value
+    pub fn size(&self) -> usize {
+        match self.0 & 0x1F {
+            3 | 4 | 11 => 2,
+            5 | 6 | 8 | 12 => 4,
+            9 | 14..=16 => 8,
+            _ => 1,
+        }
+    }
+}
+
+impl Field {
+    pub fn enumeration(number: u8, value: u8) -> Self {
+        Self::new(number, BaseType::ENUM, vec![value])
+    }
+
+    pub fn uint8(number: u8, value: u8) -> Self {
+        Self::new(number, BaseType::UINT8, vec![value])
+    }
+
+    pub fn uint16(number: u8, value: u16) -> Self {
+        Self::new(number, BaseType::UINT16, value.to_le_bytes().to_vec())
+    }
+
+    pub fn uint16_array(number: u8, values: &[u16]) -> Self {
+        let data = values.iter().flat_map(|v| v.to_le_bytes()).collect();
+        Self::new(number, BaseType::UINT16, data)
+    }
+
+    pub fn uint32(number: u8, value: u32) -> Self {
+        Self::new(number, BaseType::UINT32, value.to_le_bytes().to_vec())
+    }
+
+    fn new(number: u8, base_type: BaseType, data: Vec<u8>) -> Self {
+        Self {
+            number,
+            base_type,
+            data,
+        }
+    }
+
+    /// The first value as an unsigned integer, `None` if the field is not an integer of at most
+    /// 4 bytes.
+    pub fn as_u32(&self) -> Option<u32> {
+        let size = self.base_type.size();
+        let bytes = self.data.get(..size)?;
+        match size {
+            1 => Some(bytes[0] as u32),
+            2 => Some(u16::from_le_bytes([bytes[0], bytes[1]]) as u32),
+            4 => Some(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])),
+            _ => None,
+        }
+    }
+
+    /// All values as unsigned integers, see [`as_u32`](Self::as_u32).
+    pub fn as_u32_array(&self) -> Vec<u32> {
+        let size = self.base_type.size();
+        self.data
+            .chunks_exact(size)
+            .filter_map(|v| {
+                Self {
+                    number: self.number,
+                    base_type: self.base_type,
+                    data: v.to_vec(),
+                }
+                .as_u32()
+            })
+            .collect()
+    }
+}
+
+impl Message {
+    pub fn new(global: u16) -> Self {
+        Self {
+            global,
+            big_endian: false,
+            fields: Vec::new(),
+            developer_fields: Vec::new(),
+        }
+    }
+
+    /// Add or replace a field
+    pub fn with(mut self, field: Field) -> Self {
+        self.set_field(field);
+        self
+    }
+
+    /// Add or replace a field
+    pub fn set_field(&mut self, field: Field) {
+        match self.fields.iter_mut().find(|v| v.number == field.number) {
+            Some(v) => *v = field,
+            None => self.fields.push(field),
+        }
+    }
+
+    pub fn field(&self, number: u8) -> Option<&Field> {
+        self.fields.iter().find(|v| v.number == number)
+    }
+
+    pub fn timestamp(&self) -> Option<u32> {
+        self.field(TIMESTAMP_FIELD).and_then(Field::as_u32)
+    }
+
+    fn definition(&self) -> Result<Definition, FitError> {
+        let size =
+            |data: &[u8]| u8::try_from(data.len()).or(Err(FitError::FieldTooLong(self.global)));
+        if self.fields.len() > u8::MAX as usize || self.developer_fields.len() > u8::MAX as usize {
+            return Err(FitError::TooManyFields(self.global));
+        }
+        Ok(Definition {
+            global: self.global,
+            big_endian: self.big_endian,
+            fields: self
+                .fields
+                .iter()
+                .map(|v| Ok((v.number, size(&v.data)?, v.base_type)))
+                .collect::<Result<_, _>>()?,
+            developer_fields: self
+                .developer_fields
+                .iter()
+                .map(|v| Ok((v.number, size(&v.data)?, v.developer_index)))
+                .collect::<Result<_, _>>()?,
+        })
+    }
+}
+
+/// Convert FIT timestamp to seconds since UNIX epoch
+pub fn to_unix_secs(timestamp: u32) -> u64 {
+    timestamp as u64 + FIT_EPOCH_OFFSET
+}
+
+/// Convert seconds since UNIX epoch to FIT timestamp, saturate if out of range.
+pub fn from_unix_secs(secs: u64) -> u32 {
+    secs.saturating_sub(FIT_EPOCH_OFFSET).min(u32::MAX as u64) as u32
+}
+
+/// Encode messages into a FIT file. A new local message type is defined whenever the layout of a
+/// message changes. Fail if a message can't be defined, i.e. a field is longer than 255 bytes or
+/// there are more than 255 fields.
+pub fn encode(messages: &[Message]) -> Result<Vec<u8>, FitError> {
+    let mut data = Vec::new();
+    let mut locals: Vec<Option<Definition>> = vec![None; LOCAL_MESSAGE_TYPES];
+    let mut next_local = 0;
+    for message in messages {
+        let definition = message.definition()?;
+        let local = match locals.iter().position(|v| v.as_ref() == Some(&definition)) {
+            Some(local) => local,
+            None => {
+                let local = next_local;
+                next_local = (next_local + 1) % LOCAL_MESSAGE_TYPES;
+                write_definition(&mut data, local as u8, &definition);
+                locals[local] = Some(definition);
+                local
+            }
+        };
+
+        data.push(local as u8);
+        for field in &message.fields {
+            let mut bytes = field.data.clone();
+            if message.big_endian {
+                swap_endian(&mut bytes, field.base_type);
+            }
+            data.extend_from_slice(&bytes);
+        }
+        for field in &message.developer_fields {
+            data.extend_from_slice(&field.data);
+        }
+    }
+
+    let mut file = Vec::with_capacity(HEADER_SIZE as usize + data.len() + 2);
+    file.push(HEADER_SIZE);
+    file.push(PROTOCOL_VERSION);
+    file.extend_from_slice(&PROFILE_VERSION.to_le_bytes());
+    file.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    file.extend_from_slice(b".FIT");
+    file.extend_from_slice(&crc(&file).to_le_bytes());
+    file.extend_from_slice(&data);
+    file.extend_from_slice(&crc(&file).to_le_bytes());
+    Ok(file)
+}
+
+/// Decode messages of a FIT file, compressed timestamps are expanded into `timestamp` fields.
+pub fn decode(file: &[u8]) -> Result<Vec<Message>, FitError> {
+    let header_size = *file.first().ok_or(FitError::Truncated)? as usize;
+    if !(header_size == 12 || header_size == 14) {
+        return Err(FitError::InvalidHeader);
+    }
+    let header = file.get(..header_size).ok_or(FitError::Truncated)?;
+    if &header[8..12] != b".FIT" {
+        return Err(FitError::InvalidHeader);
+    }
+    let data_size = u32::from_le_bytes([header[4], header[5], header[6], header[7]]) as usize;
+    let end = header_size + data_size;
+    let file_crc = file.get(end..end + 2).ok_or(FitError::Truncated)?;
+    if crc(&file[..end]) != u16::from_le_bytes([file_crc[0], file_crc[1]]) {
+        return Err(FitError::CrcMismatch);
+    }
+
+    let mut reader = Reader {
+        data: &file[header_size..end],
+    };
+    let mut locals: Vec<Option<Definition>> = vec![None; LOCAL_MESSAGE_TYPES];
+    let mut messages = Vec::new();
+    let mut last_timestamp = 0u32;
+    while !reader.data.is_empty() {
+        let record_header = reader.take(1)?[0];
+        if record_header & COMPRESSED_TIMESTAMP_FLAG != 0 {
+            let local = (record_header >> 5) & 0x03;
+            let offset = (record_header & 0x1F) as u32;
+            let mut timestamp = (last_timestamp & !0x1F) + offset;
+            if offset < last_timestamp & 0x1F {
+                timestamp += 0x20;
+            }
+            last_timestamp = timestamp;
+            let definition = locals[local as usize]
+                .as_ref()
+                .ok_or(FitError::UndefinedLocalMessage(local))?;
+            let mut message = read_message(&mut reader, definition)?;
+            message.set_field(Field::uint32(TIMESTAMP_FIELD, timestamp));
+            messages.push(message);
+        } else if record_header & DEFINITION_FLAG != 0 {
+            let local = record_header & 0x0F;
+            let definition =
+                read_definition(&mut reader, record_header & DEVELOPER_DATA_FLAG != 0)?;
+            locals[local as usize] = Some(definition);
+        } else {
+            let local = record_header & 0x0F;
+            let definition = locals[local as usize]
+                .as_ref()
+                .ok_or(FitError::UndefinedLocalMessage(local))?;
+            let message = read_message(&mut reader, definition)?;
+            if let Some(timestamp) = message.timestamp() {
+                last_timestamp = timestamp;
+            }
+            messages.push(message);
+        }
+    }
+    Ok(messages)
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, size: usize) -> Result<&'a [u8], FitError> {
+        if self.data.len() < size {
+            return Err(FitError::Truncated);
+        }
+        let (head, tail) = self.data.split_at(size);
+        self.data = tail;
+        Ok(head)
+    }
+}
+
+fn read_definition(reader: &mut Reader, developer_data: bool) -> Result<Definition, FitError> {
+    let fixed = reader.take(5)?;
+    let big_endian = fixed[1] == 1;
+    let global = if big_endian {
+        u16::from_be_bytes([fixed[2], fixed[3]])
+    } else {
+        u16::from_le_bytes([fixed[2], fixed[3]])
+    };
+    let fields = reader
+        .take(fixed[4] as usize * 3)?
+        .chunks_exact(3)
+        .map(|v| (v[0], v[1], BaseType(v[2])))
+        .collect();
+    let developer_fields = if developer_data {
+        let count = reader.take(1)?[0] as usize;
+        reader
+            .take(count * 3)?
+            .chunks_exact(3)
+            .map(|v| (v[0], v[1], v[2]))
+            .collect()
+    } else {
+        Vec::new()
+    };
+    Ok(Definition {
+        global,
+        big_endian,
+        fields,
+        developer_fields,
+    })
+}
+
+fn read_message(reader: &mut Reader, definition: &Definition) -> Result<Message, FitError> {
+    let mut message = Message::new(definition.global);
+    message.big_endian = definition.big_endian;
+    for &(number, size, base_type) in &definition.fields {
+        let mut data = reader.take(size as usize)?.to_vec();
+        if definition.big_endian {
+            swap_endian(&mut data, base_type);
+        }
+        message.fields.push(Field {
+            number,
+            base_type,
+            data,
+        });
+    }
+    for &(number, size, developer_index) in &definition.developer_fields {
+        message.developer_fields.push(DeveloperField {
+            number,
+            developer_index,
+            data: reader.take(size as usize)?.to_vec(),
+        });
+    }
+    Ok(message)
+}
+
+fn write_definition(data: &mut Vec<u8>, local: u8, definition: &Definition) {
+    let mut header = DEFINITION_FLAG | local;
+    if !definition.developer_fields.is_empty() {
+        header |= DEVELOPER_DATA_FLAG;
+    }
+    data.push(header);
+    data.push(0);
+    if definition.big_endian {
+        data.push(1);
+        data.extend_from_slice(&definition.global.to_be_bytes());
+    } else {
+        data.push(0);
+        data.extend_from_slice(&definition.global.to_le_bytes());
+    }
+    data.push(definition.fields.len() as u8);
+    for &(number, size, base_type) in &definition.fields {
+        data.extend_from_slice(&[number, size, base_type.0]);
+    }
+    if !definition.developer_fields.is_empty() {
+        data.push(definition.developer_fields.len() as u8);
+        for &(number, size, developer_index) in &definition.developer_fields {
+            data.extend_from_slice(&[number, size, developer_index]);
+        }
+    }
+}
+
+fn swap_endian(data: &mut [u8], base_type: BaseType) {
+    let size = base_type.size();
+    if size > 1 {
+        data.chunks_exact_mut(size).for_each(|v| v.reverse());
+    }
+}
+
+fn crc(data: &[u8]) -> u16 {
+    const TABLE: [u16; 16] = [
+        0x0000, 0xCC01, 0xD801, 0x1400, 0xF001, 0x3C00, 0x2800, 0xE401, 0xA001, 0x6C00, 0x7800,
+        0xB401, 0x5000, 0x9C01, 0x8801, 0x4400,
+    ];
+    data.iter().fold(0, |mut crc, &byte| {
+        let tmp = TABLE[(crc & 0xF) as usize];
+        crc = (crc >> 4) & 0x0FFF;
+        crc = crc ^ tmp ^ TABLE[(byte & 0xF) as usize];
+        let tmp = TABLE[(crc & 0xF) as usize];
+        crc = (crc >> 4) & 0x0FFF;
+        crc ^ tmp ^ TABLE[(byte >> 4) as usize]
+    })
+}
+
+impl Display for FitError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::InvalidHeader => write!(f, "not a FIT file"),
+            Self::Truncated => write!(f, "the file is truncated"),
+            Self::CrcMismatch => write!(f, "CRC mismatch"),
+            Self::UndefinedLocalMessage(v) => write!(f, "undefined local message type {v}"),
+            Self::FieldTooLong(v) => write!(f, "a field of message {v} is longer than 255 bytes"),
+            Self::TooManyFields(v) => write!(f, "message {v} has more than 255 fields"),
+        }
+    }
+}
+
+impl std::error::Error for FitError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let messages = [
+            Message::new(0)
+                .with(Field::enumeration(0, 4))
+                .with(Field::uint32(TIMESTAMP_FIELD, 1000)),
+            Message::new(20)
+                .with(Field::uint32(TIMESTAMP_FIELD, 1001))
+                .with(Field::uint8(3, 90)),
+            Message::new(78).with(Field::uint16_array(0, &[800, 810, 0xFFFF])),
+            Message::new(20)
+                .with(Field::uint32(TIMESTAMP_FIELD, 1002))
+                .with(Field::uint8(3, 91)),
+        ];
+        assert_eq!(decode(&encode(&messages).unwrap()).unwrap(), messages);
+    }
+
+    #[test]
+    fn reject_field_longer_than_255_bytes() {
+        let messages = [Message::new(78).with(Field::uint16_array(0, &[0; 128]))];
+        assert_eq!(encode(&messages), Err(FitError::FieldTooLong(78)));
+
+        let messages = [Message::new(78).with(Field::uint16_array(0, &[0; 127]))];
+        assert!(encode(&messages).is_ok());
+    }
+}
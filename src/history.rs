@@ -0,0 +1,137 @@
+//! History of recorded sessions
+//!
+//! The history is the session files in the recording directory. Each session file `<id>.csv` may
+//! have a sidecar `<id>.json` storing its [`SessionMeta`], statistics are always computed from the
+//! samples.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use serde::{Deserialize, Serialize};
+
+use crate::alarm::AlarmEvent;
+use crate::session::Session;
+
+/// Information can't be derived from samples
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SessionMeta {
+    pub name: String,
+    /// Name of the device recorded the session
+    pub device: Option<String>,
+    /// Alarms raised during the session
+    pub alarms: Vec<AlarmEvent>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct HistoryEntry {
+    /// Path of the session file
+    pub path: PathBuf,
+    pub meta: SessionMeta,
+    pub start: SystemTime,
+    pub end: SystemTime,
+    pub samples: usize,
+    pub min_heart_rate: Option<u16>,
+    pub avg_heart_rate: Option<u16>,
+    pub max_heart_rate: Option<u16>,
+}
+
+impl SessionMeta {
+    fn path(session: &Path) -> PathBuf {
+        session.with_extension("json")
+    }
+
+    /// Load the sidecar of the session file, `None` if it is missing or invalid.
+    pub fn load(session: &Path) -> Option<Self> {
+        let meta = fs::read_to_string(Self::path(session)).ok()?;
+        serde_json::from_str(&meta).ok()
+    }
+
+    pub fn save(&self, session: &Path) -> io::Result<()> {
+        let meta = serde_json::to_string_pretty(self).map_err(io::Error::other)?;
+        fs::write(Self::path(session), meta)
+    }
+}
+
+impl HistoryEntry {
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let session = Session::load(path)?;
+        let meta = SessionMeta::load(path).unwrap_or_else(|| SessionMeta {
+            name: path
+                .file_stem()
+                .map_or_else(String::new, |v| v.to_string_lossy().into_owned()),
+            ..Default::default()
+        });
+        let heart_rates = session
+            .samples
+            .iter()
+            .filter(|v| v.hrm.heart_rate > 0 && !v.hrm.contact_lost())
+            .map(|v| v.hrm.heart_rate)
+            .collect::<Vec<_>>();
+        let start = session
+            .samples
+            .first()
+            .map_or(SystemTime::UNIX_EPOCH, |v| v.wall_clock);
+        Ok(Self {
+            path: path.to_path_buf(),
+            meta,
+            start,
+            end: start + session.duration(),
+            samples: session.samples.len(),
+            min_heart_rate: heart_rates.iter().min().copied(),
+            avg_heart_rate: (!heart_rates.is_empty()).then(|| {
+                (heart_rates.iter().map(|v| *v as u32).sum::<u32>() / heart_rates.len() as u32)
+                    as u16
+            }),
+            max_heart_rate: heart_rates.iter().max().copied(),
+        })
+    }
+
+    pub fn duration(&self) -> Duration {
+        self.end.duration_since(self.start).unwrap_or_default()
+    }
+
+    pub fn rename(&mut self, name: String) -> io::Result<()> {
+        let meta = SessionMeta {
+            name,
+            ..self.meta.clone()
+        };
+        meta.save(&self.path)?;
+        self.meta = meta;
+        Ok(())
+    }
+
+    /// Delete the session file and its sidecar
+    pub fn delete(self) -> io::Result<()> {
+        delete_session(&self.path)
+    }
+}
+
+/// Delete the session file and its sidecar, the sidecar may be missing.
+pub(crate) fn delete_session(path: &Path) -> io::Result<()> {
+    fs::remove_file(path)?;
+    match fs::remove_file(SessionMeta::path(path)) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
+
+/// Load all sessions in `dir`, newest first. Invalid session files are skipped, and an empty list
+/// is returned if `dir` doesn't exist.
+pub fn load(dir: &Path) -> io::Result<Vec<HistoryEntry>> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+    let mut history = entries
+        .filter_map(|v| v.ok().map(|v| v.path()))
+        .filter(|v| v.extension().is_some_and(|v| v == "csv"))
+        .filter_map(|v| HistoryEntry::load(&v).ok())
+        .filter(|v| v.samples > 0)
+        .collect::<Vec<_>>();
+    history.sort_by_key(|v| std::cmp::Reverse(v.start));
+    Ok(history)
+}
@@ -5,23 +5,42 @@ const SENSOR_CONTACT_STATUS: u8 = 1 << 1;
 const SENSOR_CONTACT_SUPPORT: u8 = 1 << 2;
 const ENERGY_EXPENDED_SUPPORT: u8 = 1 << 3;
 const RR_INTERVAL_SUPPORT: u8 = 1 << 4;
+const RESERVED_FLAGS: u8 = 0b1110_0000;
 
 /// LE Bluetooth _Heart Rate Measurement_ characteristic data represent
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct HeartRateMeasurement {
     /// Unit: bpm
     pub heart_rate: u16,
     pub sensor_contact: Option<bool>,
     /// Unit: kiloJoules
     pub energy_expended: Option<u16>,
-    /// Unit: 1/1024 seconds
-    pub rr_interval: Option<NonZeroU16>,
+    /// Unit: 1/1024 seconds. Oldest first, a single notification may carry several values.
+    pub rr_intervals: Vec<NonZeroU16>,
+}
+
+/// Reason of a _Heart Rate Measurement_ characteristic data be rejected
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HrmParseError {
+    /// The data is empty, even the flags field is missing
+    Empty,
+    /// The heart rate value field is missing or truncated
+    TruncatedHeartRate,
+    /// The _Energy Expended_ field is indicated by flags but missing or truncated
+    TruncatedEnergyExpended,
+    /// The _RR-Interval_ field is indicated by flags but its length is odd
+    OddRrIntervalBytes,
+    /// The reserved bits of flags is set
+    ReservedFlags(u8),
 }
 
 impl HeartRateMeasurement {
-    pub fn parse(raw: &[u8]) -> Option<Self> {
+    pub fn parse(raw: &[u8]) -> Result<Self, HrmParseError> {
         let mut raw = raw.iter();
-        let flags = raw.next()?;
+        let flags = *raw.next().ok_or(HrmParseError::Empty)?;
+        if flags & RESERVED_FLAGS != 0 {
+            return Err(HrmParseError::ReservedFlags(flags & RESERVED_FLAGS));
+        }
 
         let check_flag = |flag: u8| flags & flag == flag;
         fn next_u16<'a, I: Iterator<Item = &'a u8>>(iter: &mut I) -> Option<u16> {
@@ -32,23 +51,77 @@ impl HeartRateMeasurement {
             next_u16(&mut raw)
         } else {
             raw.next().map(|v| *v as u16)
-        }?;
+        }
+        .ok_or(HrmParseError::TruncatedHeartRate)?;
         let sensor_contact =
             check_flag(SENSOR_CONTACT_SUPPORT).then_some(check_flag(SENSOR_CONTACT_STATUS));
         let energy_expended = check_flag(ENERGY_EXPENDED_SUPPORT)
-            .then(|| next_u16(&mut raw))
-            .flatten();
-        let rr_interval = check_flag(RR_INTERVAL_SUPPORT)
-            .then(|| next_u16(&mut raw).and_then(NonZeroU16::new))
-            .flatten();
+            .then(|| next_u16(&mut raw).ok_or(HrmParseError::TruncatedEnergyExpended))
+            .transpose()?;
+        let mut rr_intervals = Vec::new();
+        if check_flag(RR_INTERVAL_SUPPORT) {
+            if !raw.len().is_multiple_of(2) {
+                return Err(HrmParseError::OddRrIntervalBytes);
+            }
+            while let Some(rr) = next_u16(&mut raw) {
+                rr_intervals.extend(NonZeroU16::new(rr));
+            }
+        }
 
-        Some(Self {
+        Ok(Self {
             heart_rate,
             sensor_contact,
             energy_expended,
-            rr_interval,
+            rr_intervals,
         })
     }
+
+    /// The device reports it is not in contact with the skin, so the heart rate is unreliable.
+    pub fn contact_lost(&self) -> bool {
+        self.sensor_contact == Some(false)
+    }
+
+    /// RR-Intervals in milliseconds, oldest first
+    pub fn rr_intervals_ms(&self) -> impl Iterator<Item = f32> + '_ {
+        self.rr_intervals
+            .iter()
+            .map(|v| v.get() as f32 * 1000.0 / 1024.0)
+    }
+
+    /// Encode to _Heart Rate Measurement_ characteristic data. The heart rate value is encoded
+    /// as `UINT8` whenever it fits, otherwise `UINT16`.
+    ///
+    /// For every value `x`, `HeartRateMeasurement::parse(&x.to_bytes()) == Ok(x)`.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut flags = 0;
+        // The flags field is filled at the end
+        let mut raw = vec![0];
+
+        match u8::try_from(self.heart_rate) {
+            Ok(v) => raw.push(v),
+            Err(_) => {
+                flags |= HEART_RATE_16BIT;
+                raw.extend(self.heart_rate.to_le_bytes());
+            }
+        }
+        if let Some(contact) = self.sensor_contact {
+            flags |= SENSOR_CONTACT_SUPPORT;
+            if contact {
+                flags |= SENSOR_CONTACT_STATUS;
+            }
+        }
+        if let Some(energy) = self.energy_expended {
+            flags |= ENERGY_EXPENDED_SUPPORT;
+            raw.extend(energy.to_le_bytes());
+        }
+        if !self.rr_intervals.is_empty() {
+            flags |= RR_INTERVAL_SUPPORT;
+            raw.extend(self.rr_intervals.iter().flat_map(|v| v.get().to_le_bytes()));
+        }
+
+        raw[0] = flags;
+        raw
+    }
 }
 
 impl Display for HeartRateMeasurement {
@@ -71,10 +144,166 @@ impl Display for HeartRateMeasurement {
             "Energy expended: {}",
             display_or_na(self.energy_expended, " kJ")
         )?;
+        let rr_intervals = self
+            .rr_intervals
+            .iter()
+            .map(|v| format!("{} ms", rr_to_ms(*v)))
+            .collect::<Vec<_>>();
         writeln!(
             f,
             "RR-Interval: {}",
-            display_or_na(self.rr_interval.map(|v| v.get() * 1000 / 1024), " ms")
+            if rr_intervals.is_empty() {
+                "N/A".into()
+            } else {
+                rr_intervals.join(", ")
+            }
         )
     }
 }
+
+/// Convert a RR-Interval (unit: 1/1024 seconds) to milliseconds
+pub fn rr_to_ms(rr: NonZeroU16) -> u32 {
+    rr.get() as u32 * 1000 / 1024
+}
+
+impl Display for HrmParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Empty => write!(f, "empty data"),
+            Self::TruncatedHeartRate => write!(f, "heart rate value is truncated"),
+            Self::TruncatedEnergyExpended => write!(f, "energy expended value is truncated"),
+            Self::OddRrIntervalBytes => write!(f, "RR-Interval field has odd number of bytes"),
+            Self::ReservedFlags(bits) => write!(f, "reserved flag bits are set ({bits:#010b})"),
+        }
+    }
+}
+
+impl std::error::Error for HrmParseError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn measurement(
+        heart_rate: u16,
+        sensor_contact: Option<bool>,
+        energy_expended: Option<u16>,
+        rr_intervals: &[u16],
+    ) -> HeartRateMeasurement {
+        HeartRateMeasurement {
+            heart_rate,
+            sensor_contact,
+            energy_expended,
+            rr_intervals: rr_intervals
+                .iter()
+                .filter_map(|v| NonZeroU16::new(*v))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn round_trip_every_heart_rate() {
+        for heart_rate in 0..=u16::MAX {
+            let hrm = measurement(heart_rate, None, None, &[]);
+            let bytes = hrm.to_bytes();
+            // UINT8 whenever it fits
+            let expected_len = if heart_rate <= u8::MAX as u16 { 2 } else { 3 };
+            assert_eq!(bytes.len(), expected_len, "heart rate {heart_rate}");
+            assert_eq!(HeartRateMeasurement::parse(&bytes), Ok(hrm));
+        }
+    }
+
+    #[test]
+    fn round_trip_every_flag_combination() {
+        let contacts = [None, Some(false), Some(true)];
+        let energies = [None, Some(0), Some(1), Some(u16::MAX)];
+        let rr_intervals: [&[u16]; 4] = [&[], &[1], &[800, 1024], &[u16::MAX; 9]];
+        for heart_rate in [0, 1, 72, 255, 256, u16::MAX] {
+            for contact in contacts {
+                for energy in energies {
+                    for rr in rr_intervals {
+                        let hrm = measurement(heart_rate, contact, energy, rr);
+                        assert_eq!(HeartRateMeasurement::parse(&hrm.to_bytes()), Ok(hrm));
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn round_trip_every_energy_and_rr_value() {
+        for value in 1..=u16::MAX {
+            let hrm = measurement(72, Some(true), Some(value), &[value]);
+            assert_eq!(HeartRateMeasurement::parse(&hrm.to_bytes()), Ok(hrm));
+        }
+    }
+
+    #[test]
+    fn parse_every_flags_byte() {
+        for flags in 0..=u8::MAX {
+            // Enough bytes for every optional field
+            let raw = [flags, 72, 0, 1, 0, 2, 0];
+            let result = HeartRateMeasurement::parse(&raw);
+            if flags & RESERVED_FLAGS != 0 {
+                assert_eq!(
+                    result,
+                    Err(HrmParseError::ReservedFlags(flags & RESERVED_FLAGS))
+                );
+                continue;
+            }
+            let Ok(hrm) = result else {
+                // The RR-Interval field takes the rest bytes, which may be odd
+                assert_eq!(result, Err(HrmParseError::OddRrIntervalBytes));
+                continue;
+            };
+            assert_eq!(
+                hrm.sensor_contact.is_some(),
+                flags & SENSOR_CONTACT_SUPPORT != 0
+            );
+            assert_eq!(
+                hrm.energy_expended.is_some(),
+                flags & ENERGY_EXPENDED_SUPPORT != 0
+            );
+            assert_eq!(HeartRateMeasurement::parse(&hrm.to_bytes()), Ok(hrm));
+        }
+    }
+
+    #[test]
+    fn reject_odd_rr_interval_bytes() {
+        assert_eq!(
+            HeartRateMeasurement::parse(&[RR_INTERVAL_SUPPORT, 72, 0x20]),
+            Err(HrmParseError::OddRrIntervalBytes)
+        );
+        assert_eq!(
+            HeartRateMeasurement::parse(&[RR_INTERVAL_SUPPORT, 72, 0x20, 0x03, 0x20]),
+            Err(HrmParseError::OddRrIntervalBytes)
+        );
+    }
+
+    #[test]
+    fn reject_truncated_data() {
+        assert_eq!(HeartRateMeasurement::parse(&[]), Err(HrmParseError::Empty));
+        assert_eq!(
+            HeartRateMeasurement::parse(&[0]),
+            Err(HrmParseError::TruncatedHeartRate)
+        );
+        assert_eq!(
+            HeartRateMeasurement::parse(&[HEART_RATE_16BIT, 72]),
+            Err(HrmParseError::TruncatedHeartRate)
+        );
+        assert_eq!(
+            HeartRateMeasurement::parse(&[ENERGY_EXPENDED_SUPPORT, 72]),
+            Err(HrmParseError::TruncatedEnergyExpended)
+        );
+        assert_eq!(
+            HeartRateMeasurement::parse(&[ENERGY_EXPENDED_SUPPORT, 72, 1]),
+            Err(HrmParseError::TruncatedEnergyExpended)
+        );
+        // Every truncation of a valid measurement is rejected, or parsed as a shorter one
+        let hrm = measurement(300, Some(true), Some(1000), &[800, 900]);
+        let bytes = hrm.to_bytes();
+        for len in 0..bytes.len() {
+            assert_ne!(HeartRateMeasurement::parse(&bytes[..len]), Ok(hrm.clone()));
+        }
+    }
+}
@@ -1,16 +1,44 @@
-use std::fmt::Display;
-use std::hash::{Hash, Hasher};
-use std::pin::Pin;
+use std::time::Duration;
 
-use btleplug::api::{AddressType, BDAddr, Central, Peripheral as _, bleuuid};
-use btleplug::platform::{Adapter, Peripheral, PeripheralId};
-use iced::futures::{Stream, StreamExt};
+use btleplug::api::{
+    AddressType, BDAddr, Central, CentralEvent, CentralState, CharPropFlags, Characteristic,
+    Manager as _, Peripheral as _, ScanFilter, WriteType, bleuuid,
+};
+use btleplug::platform::{Adapter, Manager, Peripheral, PeripheralId};
+use iced::futures::StreamExt;
+use log::warn;
 use uuid::Uuid;
 
 use crate::hrm::HeartRateMeasurement;
+use crate::source::{
+    BatteryLevelStream, BodySensorLocation, BoxFuture, Device, DeviceId, DeviceInformation,
+    EventStream, HeartRateDevice, HeartRateSource, HeartRateStream, SourceEvent, SourceState,
+};
 
 pub(crate) const HRS_UUID: Uuid = bleuuid::uuid_from_u16(0x180D);
 pub(crate) const HRM_UUID: Uuid = bleuuid::uuid_from_u16(0x2A37);
+pub(crate) const BODY_SENSOR_LOCATION_UUID: Uuid = bleuuid::uuid_from_u16(0x2A38);
+pub(crate) const HR_CONTROL_POINT_UUID: Uuid = bleuuid::uuid_from_u16(0x2A39);
+pub(crate) const BATTERY_LEVEL_UUID: Uuid = bleuuid::uuid_from_u16(0x2A19);
+pub(crate) const MANUFACTURER_NAME_UUID: Uuid = bleuuid::uuid_from_u16(0x2A29);
+pub(crate) const MODEL_NUMBER_UUID: Uuid = bleuuid::uuid_from_u16(0x2A24);
+pub(crate) const SERIAL_NUMBER_UUID: Uuid = bleuuid::uuid_from_u16(0x2A25);
+pub(crate) const FIRMWARE_REVISION_UUID: Uuid = bleuuid::uuid_from_u16(0x2A26);
+pub(crate) const HARDWARE_REVISION_UUID: Uuid = bleuuid::uuid_from_u16(0x2A27);
+/// Strings read from _Device Information Service_, in the order of [`DeviceInformation`] fields
+const DEVICE_INFORMATION_UUIDS: [Uuid; 5] = [
+    MANUFACTURER_NAME_UUID,
+    MODEL_NUMBER_UUID,
+    SERIAL_NUMBER_UUID,
+    FIRMWARE_REVISION_UUID,
+    HARDWARE_REVISION_UUID,
+];
+
+/// _Heart Rate Control Point_ command to reset the _Energy Expended_ field
+const RESET_ENERGY_EXPENDED: u8 = 0x01;
+/// Some devices expose the _Heart Rate Measurement_ characteristic only after re-discovery
+const DISCOVERY_ATTEMPTS: u32 = 3;
+const DISCOVERY_RETRY_DELAY: Duration = Duration::from_secs(1);
 
 /// A Bluetooth device that provided _Heart Rate Service_
 #[derive(Clone, Debug)]
@@ -20,6 +48,12 @@ pub struct HrsDevice {
     peripheral: Peripheral,
 }
 
+/// The Bluetooth adapter, discovers _Heart Rate Service_ devices
+#[derive(Clone, Debug)]
+pub struct BleSource {
+    adapter: Adapter,
+}
+
 impl HrsDevice {
     /// If the device is not discoverd by `adapter` or failed to get the properties associated with
     /// the device, the `None` returned.
@@ -42,10 +76,6 @@ impl HrsDevice {
         self.address_type
     }
 
-    pub fn name(&self) -> Option<&str> {
-        self.name.as_ref().map(|s| s.as_ref())
-    }
-
     pub async fn connect(&self) -> btleplug::Result<()> {
         self.peripheral.connect().await
     }
@@ -57,19 +87,26 @@ impl HrsDevice {
     /// Subscribe the _Heart Rate Measurement_ characteristics and return a stream of heart rate
     /// for heart rate updates. This stream will be closed when the device disconnected.
     ///
-    /// If the stream yield a `None`, it indicate the client received a new but invalid data.
-    pub async fn subscribe(
-        &self,
-    ) -> btleplug::Result<Pin<Box<dyn Stream<Item = Option<HeartRateMeasurement>> + Send + 'static>>>
-    {
-        self.peripheral.discover_services().await?;
-        let hrm_cahr = self
-            .peripheral
-            .characteristics()
-            .into_iter()
-            .find(|c| c.uuid == HRM_UUID)
-            .unwrap();
-        self.peripheral.subscribe(&hrm_cahr).await?;
+    /// If the stream yield an `Err`, it indicate the client received a new but invalid data.
+    ///
+    /// The services will be re-discovered a few times if the _Heart Rate Measurement_
+    /// characteristic is not found.
+    pub async fn subscribe(&self) -> btleplug::Result<HeartRateStream> {
+        let mut hrm_char = None;
+        for attempt in 1..=DISCOVERY_ATTEMPTS {
+            self.peripheral.discover_services().await?;
+            hrm_char = self.characteristic(HRM_UUID);
+            if hrm_char.is_some() {
+                break;
+            }
+            warn!("Heart rate characteristic not found (attempt {attempt}/{DISCOVERY_ATTEMPTS})");
+            if attempt < DISCOVERY_ATTEMPTS {
+                tokio::time::sleep(DISCOVERY_RETRY_DELAY).await;
+            }
+        }
+        let hrm_char = hrm_char
+            .ok_or_else(|| btleplug::Error::Other("heart rate characteristic not found".into()))?;
+        self.peripheral.subscribe(&hrm_char).await?;
         Ok(self
             .peripheral
             .notifications()
@@ -80,31 +117,274 @@ impl HrsDevice {
             .boxed())
     }
 
+    /// Read the _Body Sensor Location_ characteristic, return `Ok(None)` if the device doesn't
+    /// expose it. The services must be discovered first, see [`subscribe`](Self::subscribe).
+    pub async fn read_body_sensor_location(&self) -> btleplug::Result<Option<BodySensorLocation>> {
+        let Some(characteristic) = self.characteristic(BODY_SENSOR_LOCATION_UUID) else {
+            return Ok(None);
+        };
+        let value = self.peripheral.read(&characteristic).await?;
+        Ok(value.first().map(|v| BodySensorLocation::from(*v)))
+    }
+
+    /// Whether the device exposes the _Heart Rate Control Point_ characteristic, which used to
+    /// reset the _Energy Expended_. Always `false` before the services discovered.
+    pub fn supports_energy_reset(&self) -> bool {
+        self.characteristic(HR_CONTROL_POINT_UUID).is_some()
+    }
+
+    /// Reset the accumulated _Energy Expended_ via _Heart Rate Control Point_. Return
+    /// [`NotSupported`](btleplug::Error::NotSupported) if the device doesn't expose it.
+    pub async fn reset_energy_expended(&self) -> btleplug::Result<()> {
+        let characteristic = self.characteristic(HR_CONTROL_POINT_UUID).ok_or_else(|| {
+            btleplug::Error::NotSupported("Heart Rate Control Point characteristic".into())
+        })?;
+        self.peripheral
+            .write(
+                &characteristic,
+                &[RESET_ENERGY_EXPENDED],
+                WriteType::WithResponse,
+            )
+            .await
+    }
+
+    /// Read the _Battery Level_ characteristic and subscribe it if the device supports
+    /// notification, return `Ok(None)` if the device doesn't expose it. The returned stream
+    /// yield nothing if notification is not supported, and will be closed when the device
+    /// disconnected. The services must be discovered first, see [`subscribe`](Self::subscribe).
+    pub async fn subscribe_battery_level(
+        &self,
+    ) -> btleplug::Result<Option<(u8, BatteryLevelStream)>> {
+        let Some(characteristic) = self.characteristic(BATTERY_LEVEL_UUID) else {
+            return Ok(None);
+        };
+        let Some(level) = self
+            .peripheral
+            .read(&characteristic)
+            .await?
+            .first()
+            .copied()
+        else {
+            return Ok(None);
+        };
+        if !characteristic.properties.contains(CharPropFlags::NOTIFY) {
+            return Ok(Some((level, iced::futures::stream::empty().boxed())));
+        }
+
+        self.peripheral.subscribe(&characteristic).await?;
+        let stream = self
+            .peripheral
+            .notifications()
+            .await?
+            .filter_map(async |v| {
+                (v.uuid == BATTERY_LEVEL_UUID)
+                    .then(|| v.value.first().copied())
+                    .flatten()
+            })
+            .boxed();
+        Ok(Some((level, stream)))
+    }
+
+    /// Read the strings of _Device Information Service_. The services must be discovered first,
+    /// see [`subscribe`](Self::subscribe).
+    ///
+    /// A string failed to read is left empty, an error is returned only if every read failed.
+    pub async fn read_device_information(&self) -> btleplug::Result<DeviceInformation> {
+        let mut strings: [Option<String>; 5] = Default::default();
+        let mut last_error = None;
+        for (string, uuid) in strings.iter_mut().zip(DEVICE_INFORMATION_UUIDS) {
+            match self.read_string(uuid).await {
+                Ok(value) => *string = value,
+                Err(e) => {
+                    warn!("Failed to read device information {uuid}: {e}");
+                    last_error = Some(e);
+                }
+            }
+        }
+        if let Some(e) = last_error
+            && strings.iter().all(Option::is_none)
+        {
+            return Err(e);
+        }
+
+        let [
+            manufacturer_name,
+            model_number,
+            serial_number,
+            firmware_revision,
+            hardware_revision,
+        ] = strings;
+        Ok(DeviceInformation {
+            manufacturer_name,
+            model_number,
+            serial_number,
+            firmware_revision,
+            hardware_revision,
+        })
+    }
+
     pub fn is_connected(&self) -> impl Future<Output = btleplug::Result<bool>> + Send + 'static {
         let device = self.peripheral.clone();
         async move { device.is_connected().await }
     }
 }
 
-impl Display for HrsDevice {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match &self.name {
-            Some(name) => write!(f, "{name}"),
-            None => write!(f, "{}", self.address()),
-        }
+impl HrsDevice {
+    /// Find a discovered characteristic by its UUID
+    fn characteristic(&self, uuid: Uuid) -> Option<Characteristic> {
+        self.peripheral
+            .characteristics()
+            .into_iter()
+            .find(|c| c.uuid == uuid)
+    }
+
+    /// Read a UTF-8 string characteristic, return `Ok(None)` if the device doesn't expose it.
+    async fn read_string(&self, uuid: Uuid) -> btleplug::Result<Option<String>> {
+        let Some(characteristic) = self.characteristic(uuid) else {
+            return Ok(None);
+        };
+        let value = self.peripheral.read(&characteristic).await?;
+        Ok(Some(
+            String::from_utf8_lossy(&value)
+                .trim_end_matches('\0')
+                .trim()
+                .to_string(),
+        ))
     }
 }
 
-impl Hash for HrsDevice {
-    fn hash<H: Hasher>(&self, state: &mut H) {
-        std::hash::Hash::hash(&self.address(), state)
+impl HeartRateDevice for HrsDevice {
+    fn id(&self) -> DeviceId {
+        DeviceId::new(self.peripheral.id().to_string())
+    }
+
+    /// Fallback to the address if the device has no name.
+    fn name(&self) -> Option<String> {
+        Some(
+            self.name
+                .clone()
+                .unwrap_or_else(|| self.address().to_string()),
+        )
+    }
+
+    fn connect(&self) -> BoxFuture<crate::source::Result<()>> {
+        let device = self.clone();
+        Box::pin(async move { Ok(HrsDevice::connect(&device).await?) })
+    }
+
+    fn disconnect(&self) -> BoxFuture<crate::source::Result<()>> {
+        let device = self.clone();
+        Box::pin(async move { Ok(HrsDevice::disconnect(&device).await?) })
+    }
+
+    fn is_connected(&self) -> BoxFuture<crate::source::Result<bool>> {
+        let connected = HrsDevice::is_connected(self);
+        Box::pin(async move { Ok(connected.await?) })
+    }
+
+    fn subscribe(&self) -> BoxFuture<crate::source::Result<HeartRateStream>> {
+        let device = self.clone();
+        Box::pin(async move { Ok(HrsDevice::subscribe(&device).await?) })
+    }
+
+    fn read_body_sensor_location(
+        &self,
+    ) -> BoxFuture<crate::source::Result<Option<BodySensorLocation>>> {
+        let device = self.clone();
+        Box::pin(async move { Ok(HrsDevice::read_body_sensor_location(&device).await?) })
+    }
+
+    fn subscribe_battery_level(
+        &self,
+    ) -> BoxFuture<crate::source::Result<Option<(u8, BatteryLevelStream)>>> {
+        let device = self.clone();
+        Box::pin(async move { Ok(HrsDevice::subscribe_battery_level(&device).await?) })
+    }
+
+    fn read_device_information(&self) -> BoxFuture<crate::source::Result<DeviceInformation>> {
+        let device = self.clone();
+        Box::pin(async move { Ok(HrsDevice::read_device_information(&device).await?) })
+    }
+
+    fn supports_energy_reset(&self) -> bool {
+        HrsDevice::supports_energy_reset(self)
+    }
+
+    fn reset_energy_expended(&self) -> BoxFuture<crate::source::Result<()>> {
+        let device = self.clone();
+        Box::pin(async move { Ok(HrsDevice::reset_energy_expended(&device).await?) })
+    }
+}
+
+impl BleSource {
+    /// Use the first Bluetooth adapter of the system
+    pub async fn new() -> btleplug::Result<Self> {
+        let adapter = Manager::new()
+            .await?
+            .adapters()
+            .await?
+            .into_iter()
+            .next()
+            .ok_or(btleplug::Error::DeviceNotFound)?;
+        Ok(Self { adapter })
     }
 }
 
-impl PartialEq for HrsDevice {
-    fn eq(&self, other: &Self) -> bool {
-        self.address() == other.address()
+impl HeartRateSource for BleSource {
+    fn state(&self) -> BoxFuture<crate::source::Result<SourceState>> {
+        let adapter = self.adapter.clone();
+        Box::pin(async move { Ok(adapter.adapter_state().await?.into()) })
+    }
+
+    fn events(&self) -> BoxFuture<crate::source::Result<EventStream>> {
+        let adapter = self.adapter.clone();
+        Box::pin(async move {
+            let events = adapter.events().await?;
+            let stream = events.filter_map(move |event| {
+                let adapter = adapter.clone();
+                async move {
+                    match event {
+                        CentralEvent::DeviceDiscovered(id) => HrsDevice::from_id(&adapter, &id)
+                            .await
+                            .map(|d| SourceEvent::DeviceDiscovered(Device::new(d))),
+                        CentralEvent::DeviceDisconnected(id) => Some(
+                            SourceEvent::DeviceDisconnected(DeviceId::new(id.to_string())),
+                        ),
+                        CentralEvent::StateUpdate(state) => {
+                            Some(SourceEvent::StateUpdate(state.into()))
+                        }
+                        _ => None,
+                    }
+                }
+            });
+            Ok(stream.boxed() as EventStream)
+        })
+    }
+
+    fn start_scan(&self) -> BoxFuture<crate::source::Result<()>> {
+        let adapter = self.adapter.clone();
+        Box::pin(async move {
+            adapter
+                .start_scan(ScanFilter {
+                    services: vec![HRS_UUID],
+                })
+                .await?;
+            Ok(())
+        })
+    }
+
+    fn stop_scan(&self) -> BoxFuture<crate::source::Result<()>> {
+        let adapter = self.adapter.clone();
+        Box::pin(async move { Ok(adapter.stop_scan().await?) })
     }
 }
 
-impl Eq for HrsDevice {}
+impl From<CentralState> for SourceState {
+    fn from(value: CentralState) -> Self {
+        match value {
+            CentralState::Unknown => SourceState::Unknown,
+            CentralState::PoweredOn => SourceState::PoweredOn,
+            CentralState::PoweredOff => SourceState::PoweredOff,
+        }
+    }
+}
@@ -0,0 +1,200 @@
+use std::collections::VecDeque;
+use std::fmt::Display;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+/// Time window which the HRV metrics computed over
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum HrvWindow {
+    #[serde(rename = "30s")]
+    Seconds30,
+    #[default]
+    #[serde(rename = "1min")]
+    Minute1,
+    #[serde(rename = "5min")]
+    Minutes5,
+}
+
+impl HrvWindow {
+    pub const ALL: &[Self] = &[Self::Seconds30, Self::Minute1, Self::Minutes5];
+
+    pub fn duration(&self) -> Duration {
+        match self {
+            Self::Seconds30 => Duration::from_secs(30),
+            Self::Minute1 => Duration::from_mins(1),
+            Self::Minutes5 => Duration::from_mins(5),
+        }
+    }
+}
+
+impl Display for HrvWindow {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let string = match self {
+            Self::Seconds30 => "30 s",
+            Self::Minute1 => "1 min",
+            Self::Minutes5 => "5 min",
+        };
+        f.write_str(string)
+    }
+}
+
+/// Heart rate variability metrics, all time values unit: ms
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HrvMetrics {
+    pub mean_nn: f32,
+    pub sdnn: f32,
+    pub rmssd: f32,
+    /// Unit: %
+    pub pnn50: f32,
+}
+
+/// Rolling HRV metrics computed from a RR-Interval stream. Only the most recent intervals whose
+/// total duration fits in the window are kept.
+#[derive(Debug, Clone)]
+pub struct Hrv {
+    window: Duration,
+    /// Intervals and whether each one follows a gap (i.e. not successive to the previous one),
+    /// unit: ms
+    rr_intervals: VecDeque<(f32, bool)>,
+    /// Sum of `rr_intervals`, unit: ms
+    total: f32,
+    /// The next interval follows a gap
+    gap: bool,
+}
+
+impl Hrv {
+    pub fn new(window: HrvWindow) -> Self {
+        Self {
+            window: window.duration(),
+            rr_intervals: VecDeque::new(),
+            total: 0.0,
+            gap: false,
+        }
+    }
+
+    pub fn set_window(&mut self, window: HrvWindow) {
+        self.window = window.duration();
+        self.evict();
+    }
+
+    /// Push a new RR-Interval, unit: ms
+    pub fn push(&mut self, rr: f32) {
+        self.rr_intervals.push_back((rr, self.gap));
+        self.gap = false;
+        self.total += rr;
+        self.evict();
+    }
+
+    /// Mark a dropped interval, so the intervals before and after it are not taken as successive.
+    pub fn push_gap(&mut self) {
+        self.gap = true;
+    }
+
+    pub fn clear(&mut self) {
+        self.rr_intervals.clear();
+        self.total = 0.0;
+        self.gap = false;
+    }
+
+    /// Compute the metrics of current window, `None` if there are no successive intervals.
+    pub fn metrics(&self) -> Option<HrvMetrics> {
+        let n = self.rr_intervals.len();
+        // Successive differences are never taken across a gap
+        let diffs = self
+            .rr_intervals
+            .iter()
+            .zip(self.rr_intervals.iter().skip(1))
+            .filter(|(_, b)| !b.1)
+            .map(|(a, b)| b.0 - a.0)
+            .collect::<Vec<_>>();
+        if diffs.is_empty() {
+            return None;
+        }
+
+        let mean_nn = self.total / n as f32;
+        let variance = self
+            .rr_intervals
+            .iter()
+            .map(|v| (v.0 - mean_nn).powi(2))
+            .sum::<f32>()
+            / (n - 1) as f32;
+
+        let (square_sum, nn50) = diffs.iter().fold((0.0, 0), |(square_sum, nn50), d| {
+            (square_sum + d * d, nn50 + (d.abs() > 50.0) as usize)
+        });
+
+        Some(HrvMetrics {
+            mean_nn,
+            sdnn: variance.sqrt(),
+            rmssd: (square_sum / diffs.len() as f32).sqrt(),
+            pnn50: nn50 as f32 * 100.0 / diffs.len() as f32,
+        })
+    }
+
+    fn evict(&mut self) {
+        let window = self.window.as_millis() as f32;
+        while self.total > window
+            && let Some((rr, _)) = self.rr_intervals.pop_front()
+        {
+            self.total -= rr;
+        }
+        if self.rr_intervals.is_empty() {
+            self.total = 0.0;
+        }
+    }
+}
+
+impl Display for HrvMetrics {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "RMSSD: {:.0} ms", self.rmssd)?;
+        writeln!(f, "SDNN: {:.0} ms", self.sdnn)?;
+        writeln!(f, "pNN50: {:.1} %", self.pnn50)?;
+        writeln!(f, "Mean NN: {:.0} ms", self.mean_nn)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn successive_differences() {
+        let mut hrv = Hrv::new(HrvWindow::Minute1);
+        [800.0, 860.0, 840.0]
+            .into_iter()
+            .for_each(|rr| hrv.push(rr));
+        let metrics = hrv.metrics().unwrap();
+        assert_eq!(metrics.mean_nn, 2500.0 / 3.0);
+        assert_eq!(
+            metrics.rmssd,
+            ((60.0f32.powi(2) + 20.0f32.powi(2)) / 2.0).sqrt()
+        );
+        assert_eq!(metrics.pnn50, 50.0);
+    }
+
+    #[test]
+    fn no_difference_across_gap() {
+        let mut hrv = Hrv::new(HrvWindow::Minute1);
+        hrv.push(800.0);
+        hrv.push_gap();
+        hrv.push(1000.0);
+        assert_eq!(hrv.metrics(), None);
+
+        hrv.push(1020.0);
+        let metrics = hrv.metrics().unwrap();
+        assert_eq!(metrics.rmssd, 20.0);
+        assert_eq!(metrics.pnn50, 0.0);
+    }
+
+    #[test]
+    fn clear_resets_gap() {
+        let mut hrv = Hrv::new(HrvWindow::Minute1);
+        hrv.push(800.0);
+        hrv.push_gap();
+        hrv.clear();
+        hrv.push(800.0);
+        hrv.push(900.0);
+        assert_eq!(hrv.metrics().unwrap().rmssd, 100.0);
+    }
+}
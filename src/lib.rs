@@ -1,7 +1,23 @@
+mod alarm;
 mod app;
+mod artifact;
 mod config;
-mod hrm;
-mod hrs_device;
+mod datetime;
+pub mod export;
+pub mod fit;
+mod history;
+pub mod hrm;
+pub mod hrs_device;
+mod hrv;
 mod locales;
+pub mod merge;
+mod recorder;
+pub mod replay;
+mod series;
+pub mod session;
+pub mod simulator;
+pub mod source;
+mod summary;
+mod zones;
 
 pub use app::App;
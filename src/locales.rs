@@ -2,6 +2,9 @@ use std::fmt::Display;
 
 use serde::{Deserialize, Serialize};
 
+use crate::alarm::AlarmKind;
+use crate::source::BodySensorLocation;
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Default, Serialize, Deserialize)]
 pub enum Language {
     #[default]
@@ -20,10 +23,72 @@ pub enum TranslateItem {
     ConnectButton,
     ConnectingButton,
     DisconnectButton,
+    ResetEnergyButton,
+    PauseButton,
+    ResumeButton,
+    MonitorPage,
+    HistoryPage,
+    HistoryEmpty,
+    HistoryDisabled,
+    DeviceLabel,
+    RenameButton,
+    DeleteButton,
+    ConfirmDeleteButton,
+    ExportLabel,
+    ExportedLabel,
+    SummaryTitle,
+    DurationLabel,
+    HeartRateRangeLabel,
+    TimeInZonesLabel,
+    EnergyExpendedLabel,
+    InvalidPacketsLabel,
+    HrZonesSetting,
+    MaxHeartRateSetting,
+    RestingHeartRateSetting,
+    ZoneColorsSetting,
+    NoContactSetting,
+    NoContactLabel,
+    StaleTimeoutSetting,
+    StaleResubscribeSetting,
+    StaleLabel,
+    AlarmsSetting,
+    AlarmHighSetting,
+    AlarmLowSetting,
+    AlarmHysteresisSetting,
+    AlarmMinDurationSetting,
+    HighAlarm,
+    LowAlarm,
+    AlarmSinceLabel,
+    AlarmsLabel,
     SettingsTitle,
     ShowHeartRateWindowSetting,
     LockHeartRateWindowSetting,
     HeartRateWindowOpaqueSetting,
+    ShowHrvSetting,
+    ShowBatterySetting,
+    ShowTrendSetting,
+    TrendWindowSetting,
+    ShowRrSetting,
+    ResetZoomButton,
+    BatteryWarningSetting,
+    BatteryLabel,
+    DeviceDetailsButton,
+    HrvWindowSetting,
+    ArtifactThresholdSetting,
+    ArtifactCorrectionSetting,
+    ArtifactsLabel,
+    RecordSessionsSetting,
+    RecordDirectorySetting,
+    RecordingLabel,
+    SensorLocationLabel,
+    SensorLocationOther,
+    SensorLocationChest,
+    SensorLocationWrist,
+    SensorLocationFinger,
+    SensorLocationHand,
+    SensorLocationEarLobe,
+    SensorLocationFoot,
+    SensorLocationUnknown,
 }
 
 impl Language {
@@ -56,32 +121,187 @@ impl TranslateItem {
     }
 }
 
+impl From<BodySensorLocation> for TranslateItem {
+    fn from(value: BodySensorLocation) -> Self {
+        use BodySensorLocation::*;
+        match value {
+            Other => TranslateItem::SensorLocationOther,
+            Chest => TranslateItem::SensorLocationChest,
+            Wrist => TranslateItem::SensorLocationWrist,
+            Finger => TranslateItem::SensorLocationFinger,
+            Hand => TranslateItem::SensorLocationHand,
+            EarLobe => TranslateItem::SensorLocationEarLobe,
+            Foot => TranslateItem::SensorLocationFoot,
+            Reserved(_) => TranslateItem::SensorLocationUnknown,
+        }
+    }
+}
+
+impl From<AlarmKind> for TranslateItem {
+    fn from(value: AlarmKind) -> Self {
+        match value {
+            AlarmKind::High => TranslateItem::HighAlarm,
+            AlarmKind::Low => TranslateItem::LowAlarm,
+        }
+    }
+}
+
 fn translate(lang: Language, key: TranslateItem) -> &'static str {
     use Language::*;
     use TranslateItem::*;
     match (lang, key) {
-        (English, UnknownAdapterState) => "Unknown Bluetooth adapter state\nI CANNOT WORK!",
+        (English, UnknownAdapterState) => {
+            "Unknown Bluetooth adapter state\nI CANNOT WORK!\nRun with --simulate to try without Bluetooth."
+        }
         (English, AdapterPowereddOff) => "Please turn on your Bluetooth adapter!",
         (English, ScanTitle) => "Discoverd devices",
         (English, ConnectedTitle) => "Connected:",
         (English, ConnectButton) => "Connect",
         (English, ConnectingButton) => "Connecting",
         (English, DisconnectButton) => "Disconnect",
+        (English, ResetEnergyButton) => "Reset energy expended",
+        (English, PauseButton) => "Pause",
+        (English, ResumeButton) => "Resume",
+        (English, MonitorPage) => "Monitor",
+        (English, HistoryPage) => "History",
+        (English, HistoryEmpty) => "No recorded sessions",
+        (English, HistoryDisabled) => {
+            "Enable \"Record sessions\" in settings to keep new sessions."
+        }
+        (English, DeviceLabel) => "Device:",
+        (English, RenameButton) => "Rename",
+        (English, DeleteButton) => "Delete",
+        (English, ConfirmDeleteButton) => "Confirm delete",
+        (English, ExportLabel) => "Export:",
+        (English, ExportedLabel) => "Exported to",
+        (English, SummaryTitle) => "Last session",
+        (English, DurationLabel) => "Duration:",
+        (English, HeartRateRangeLabel) => "Heart rate (min/avg/max):",
+        (English, TimeInZonesLabel) => "Time in zones:",
+        (English, EnergyExpendedLabel) => "Energy expended:",
+        (English, InvalidPacketsLabel) => "Invalid packets:",
+        (English, HrZonesSetting) => "Heart rate zones",
+        (English, MaxHeartRateSetting) => "Max heart rate:",
+        (English, RestingHeartRateSetting) => "Resting heart rate:",
+        (English, ZoneColorsSetting) => "Color heart rate window by zone",
+        (English, NoContactSetting) => "Hide heart rate without sensor contact",
+        (English, NoContactLabel) => "No sensor contact",
+        (English, StaleTimeoutSetting) => "Stale heart rate timeout:",
+        (English, StaleResubscribeSetting) => "Resubscribe stale heart rate",
+        (English, StaleLabel) => "No heart rate received for",
+        (English, AlarmsSetting) => "Heart rate alarms",
+        (English, AlarmHighSetting) => "High limit:",
+        (English, AlarmLowSetting) => "Low limit:",
+        (English, AlarmHysteresisSetting) => "Hysteresis:",
+        (English, AlarmMinDurationSetting) => "Minimum duration:",
+        (English, HighAlarm) => "High heart rate",
+        (English, LowAlarm) => "Low heart rate",
+        (English, AlarmSinceLabel) => "since",
+        (English, AlarmsLabel) => "Alarms:",
         (English, SettingsTitle) => "Settings",
         (English, ShowHeartRateWindowSetting) => "Show heart rate window",
         (English, LockHeartRateWindowSetting) => "Lock heart rate window",
         (English, HeartRateWindowOpaqueSetting) => "Heart rate window opaque:",
+        (English, ShowHrvSetting) => "Show HRV in heart rate window",
+        (English, ShowBatterySetting) => "Show battery in heart rate window",
+        (English, ShowTrendSetting) => "Show trend in heart rate window",
+        (English, TrendWindowSetting) => "Trend window:",
+        (English, ShowRrSetting) => "Show RR-Intervals",
+        (English, ResetZoomButton) => "Reset zoom",
+        (English, BatteryWarningSetting) => "Low battery warning:",
+        (English, BatteryLabel) => "Battery:",
+        (English, DeviceDetailsButton) => "Details",
+        (English, HrvWindowSetting) => "HRV window:",
+        (English, ArtifactThresholdSetting) => "RR artifact threshold:",
+        (English, ArtifactCorrectionSetting) => "Correct RR artifacts",
+        (English, ArtifactsLabel) => "Artifacts:",
+        (English, RecordSessionsSetting) => "Record sessions",
+        (English, RecordDirectorySetting) => "Recording folder",
+        (English, RecordingLabel) => "● REC",
+        (English, SensorLocationLabel) => "Sensor location:",
+        (English, SensorLocationOther) => "Other",
+        (English, SensorLocationChest) => "Chest",
+        (English, SensorLocationWrist) => "Wrist",
+        (English, SensorLocationFinger) => "Finger",
+        (English, SensorLocationHand) => "Hand",
+        (English, SensorLocationEarLobe) => "Ear lobe",
+        (English, SensorLocationFoot) => "Foot",
+        (English, SensorLocationUnknown) => "Unknown",
 
-        (Chinese, UnknownAdapterState) => "蓝牙状态未知，无法继续",
+        (Chinese, UnknownAdapterState) => {
+            "蓝牙状态未知，无法继续\n使用 --simulate 参数运行可在没有蓝牙时体验"
+        }
         (Chinese, AdapterPowereddOff) => "不是，哥们儿！把蓝牙给开开！",
         (Chinese, ScanTitle) => "扫描到的设备",
         (Chinese, ConnectedTitle) => "连接到：",
         (Chinese, ConnectButton) => "连接",
         (Chinese, ConnectingButton) => "正在连接",
         (Chinese, DisconnectButton) => "断开设备",
+        (Chinese, ResetEnergyButton) => "重置能量消耗",
+        (Chinese, PauseButton) => "暂停",
+        (Chinese, ResumeButton) => "继续",
+        (Chinese, MonitorPage) => "监测",
+        (Chinese, HistoryPage) => "历史",
+        (Chinese, HistoryEmpty) => "没有已记录的会话",
+        (Chinese, HistoryDisabled) => "在设置中开启“记录会话”以保存新的会话。",
+        (Chinese, DeviceLabel) => "设备：",
+        (Chinese, RenameButton) => "重命名",
+        (Chinese, DeleteButton) => "删除",
+        (Chinese, ConfirmDeleteButton) => "确认删除",
+        (Chinese, ExportLabel) => "导出：",
+        (Chinese, ExportedLabel) => "已导出到",
+        (Chinese, SummaryTitle) => "上次会话",
+        (Chinese, DurationLabel) => "时长：",
+        (Chinese, HeartRateRangeLabel) => "心率（最低/平均/最高）：",
+        (Chinese, TimeInZonesLabel) => "各区间时间：",
+        (Chinese, EnergyExpendedLabel) => "能量消耗：",
+        (Chinese, InvalidPacketsLabel) => "无效数据包：",
+        (Chinese, HrZonesSetting) => "心率区间",
+        (Chinese, MaxHeartRateSetting) => "最大心率：",
+        (Chinese, RestingHeartRateSetting) => "静息心率：",
+        (Chinese, ZoneColorsSetting) => "按区间为心率窗口着色",
+        (Chinese, NoContactSetting) => "无传感器接触时隐藏心率",
+        (Chinese, NoContactLabel) => "传感器未接触",
+        (Chinese, StaleTimeoutSetting) => "心率过期超时：",
+        (Chinese, StaleResubscribeSetting) => "心率过期时重新订阅",
+        (Chinese, StaleLabel) => "未收到心率已",
+        (Chinese, AlarmsSetting) => "心率警报",
+        (Chinese, AlarmHighSetting) => "上限：",
+        (Chinese, AlarmLowSetting) => "下限：",
+        (Chinese, AlarmHysteresisSetting) => "回差：",
+        (Chinese, AlarmMinDurationSetting) => "最短持续时间：",
+        (Chinese, HighAlarm) => "心率过高",
+        (Chinese, LowAlarm) => "心率过低",
+        (Chinese, AlarmSinceLabel) => "开始于",
+        (Chinese, AlarmsLabel) => "警报：",
         (Chinese, SettingsTitle) => "设置",
         (Chinese, ShowHeartRateWindowSetting) => "显示心率窗口",
         (Chinese, LockHeartRateWindowSetting) => "锁定心率窗口",
         (Chinese, HeartRateWindowOpaqueSetting) => "心率窗口不透明度：",
+        (Chinese, ShowHrvSetting) => "在心率窗口显示心率变异性",
+        (Chinese, ShowBatterySetting) => "在心率窗口显示电量",
+        (Chinese, ShowTrendSetting) => "在心率窗口显示趋势",
+        (Chinese, TrendWindowSetting) => "趋势时长：",
+        (Chinese, ShowRrSetting) => "显示 RR 间期",
+        (Chinese, ResetZoomButton) => "重置缩放",
+        (Chinese, BatteryWarningSetting) => "低电量警告：",
+        (Chinese, BatteryLabel) => "电量：",
+        (Chinese, DeviceDetailsButton) => "详情",
+        (Chinese, HrvWindowSetting) => "心率变异性窗口：",
+        (Chinese, ArtifactThresholdSetting) => "RR 伪迹阈值：",
+        (Chinese, ArtifactCorrectionSetting) => "修正 RR 伪迹",
+        (Chinese, ArtifactsLabel) => "伪迹：",
+        (Chinese, RecordSessionsSetting) => "记录会话",
+        (Chinese, RecordDirectorySetting) => "记录保存目录",
+        (Chinese, RecordingLabel) => "● 记录中",
+        (Chinese, SensorLocationLabel) => "传感器位置：",
+        (Chinese, SensorLocationOther) => "其他",
+        (Chinese, SensorLocationChest) => "胸部",
+        (Chinese, SensorLocationWrist) => "手腕",
+        (Chinese, SensorLocationFinger) => "手指",
+        (Chinese, SensorLocationHand) => "手掌",
+        (Chinese, SensorLocationEarLobe) => "耳垂",
+        (Chinese, SensorLocationFoot) => "脚部",
+        (Chinese, SensorLocationUnknown) => "未知",
     }
 }
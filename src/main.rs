@@ -3,19 +3,137 @@
     windows_subsystem = "windows"
 )]
 
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
 use hr_view::App;
+use hr_view::export::{self, Format};
+use hr_view::merge::{self, MergeOptions};
+use hr_view::replay::{ReplaySource, Speed};
+use hr_view::simulator::{SimulatedSource, SimulatorSettings};
+use hr_view::source::HeartRateSource;
 
 fn main() -> iced::Result {
     #[cfg(debug_assertions)]
     env_logger::init();
 
-    iced::daemon(App::boot, App::update, App::view)
-        .title("Heart Rate View")
-        .subscription(App::subscription)
-        .theme(App::theme)
-        .style(|_, theme| iced::theme::Style {
-            background_color: iced::Color::TRANSPARENT,
-            text_color: theme.palette().text,
-        })
-        .run()
+    let mut simulate = false;
+    let mut simulator = SimulatorSettings::default();
+    let mut replay = None;
+    let mut replay_speed = Speed(1);
+    let mut exports = Vec::new();
+    let mut export_formats = Vec::new();
+    let mut merge_activity = None;
+    let mut merge_session = None;
+    let mut merge_options = MergeOptions::default();
+    for arg in std::env::args().skip(1) {
+        if arg == "--simulate" {
+            simulate = true;
+        } else if let Some(path) = arg.strip_prefix("--replay=") {
+            replay = Some(PathBuf::from(path));
+        } else if let Some(speed) = arg.strip_prefix("--replay-speed=")
+            && let Some(speed) = speed.parse().ok().filter(|v| *v > 0)
+        {
+            replay_speed = Speed(speed);
+        } else if let Some(path) = arg.strip_prefix("--export=") {
+            exports.push(PathBuf::from(path));
+        } else if let Some(format) = arg.strip_prefix("--export-format=")
+            && let Some(format) = Format::ALL.iter().find(|v| v.extension() == format)
+        {
+            export_formats.push(*format);
+        } else if let Some(path) = arg.strip_prefix("--merge=") {
+            merge_activity = Some(PathBuf::from(path));
+        } else if let Some(path) = arg.strip_prefix("--merge-session=") {
+            merge_session = Some(PathBuf::from(path));
+        } else if let Some(offset) = arg.strip_prefix("--merge-offset=")
+            && let Ok(offset) = offset.parse()
+        {
+            merge_options.clock_offset = offset;
+        } else if let Some(gap) = arg.strip_prefix("--merge-max-gap=") {
+            match gap.parse().map(Duration::try_from_secs_f64) {
+                Ok(Ok(gap)) => merge_options.max_gap = gap,
+                _ => {
+                    eprintln!(
+                        "Invalid argument: {arg}, expect --merge-max-gap=<seconds> (a number >= 0)"
+                    );
+                    std::process::exit(1);
+                }
+            }
+        } else if !simulator.apply_arg(&arg) {
+            eprintln!("Ignored unknown or invalid argument: {arg}");
+        }
+    }
+
+    if let Some(activity) = merge_activity {
+        let Some(session) = merge_session else {
+            eprintln!("Missing the session to merge, use --merge-session=<file>");
+            std::process::exit(1);
+        };
+        match merge::merge_file(&activity, &session, &merge_options) {
+            Ok((output, count)) => {
+                println!(
+                    "Merged heart rate into {count} points, saved to {}",
+                    output.display()
+                );
+                std::process::exit(0);
+            }
+            Err(e) => {
+                eprintln!("Failed to merge into '{}': {e}", activity.display());
+                std::process::exit(1);
+            }
+        }
+    }
+
+    if !exports.is_empty() {
+        if export_formats.is_empty() {
+            export_formats.extend_from_slice(Format::ALL);
+        }
+        let mut failed = false;
+        for path in &exports {
+            for format in &export_formats {
+                match export::export_file(path, *format) {
+                    Ok(output) => println!("Exported {}", output.display()),
+                    Err(e) => {
+                        eprintln!("Failed to export '{}' to {format}: {e}", path.display());
+                        failed = true;
+                    }
+                }
+            }
+        }
+        std::process::exit(if failed { 1 } else { 0 });
+    }
+
+    let mut playback = None;
+    let source: Option<Arc<dyn HeartRateSource>> = match replay {
+        Some(path) => match ReplaySource::new(&path, replay_speed) {
+            Ok(source) => {
+                playback = Some(source.playback());
+                Some(Arc::new(source))
+            }
+            Err(e) => {
+                eprintln!("Failed to load session '{}': {e}", path.display());
+                std::process::exit(1);
+            }
+        },
+        None if simulate => Some(Arc::new(SimulatedSource::new(simulator))),
+        None => None,
+    };
+
+    iced::daemon(
+        move || match &source {
+            Some(source) => App::boot_with(source.clone(), playback.clone()),
+            None => App::boot(),
+        },
+        App::update,
+        App::view,
+    )
+    .title("Heart Rate View")
+    .subscription(App::subscription)
+    .theme(App::theme)
+    .style(|_, theme| iced::theme::Style {
+        background_color: iced::Color::TRANSPARENT,
+        text_color: theme.palette().text,
+    })
+    .run()
 }
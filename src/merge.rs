@@ -0,0 +1,589 @@
+//! Merge the heart rate of a recorded session into an activity recorded by another device (e.g. a
+//! bike computer without heart rate strap), matching by timestamp.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use crate::datetime::UtcDateTime;
+use crate::fit::{self, Field, Message};
+use crate::session::Session;
+
+/// Heart rate within this time of an activity point is used as is, if it can't be interpolated.
+const NEAREST_TOLERANCE: Duration = Duration::from_secs(1);
+const GPX_TPX_NAMESPACE: &str = "http://www.garmin.com/xmlschemas/TrackPointExtension/v1";
+
+mod fit_global {
+    pub const SESSION: u16 = 18;
+    pub const LAP: u16 = 19;
+    pub const RECORD: u16 = 20;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MergeOptions {
+    /// Added to the timestamps of the session to match the clock of the activity, unit: seconds
+    pub clock_offset: f64,
+    /// Heart rate is interpolated between two samples at most this far apart
+    pub max_gap: Duration,
+}
+
+/// Heart rate samples of a session on the activity's clock
+struct HeartRateTrack {
+    /// Ordered by time, unit of time: ms since UNIX epoch
+    samples: Vec<(i64, u16)>,
+    max_gap_ms: i64,
+}
+
+impl Default for MergeOptions {
+    fn default() -> Self {
+        Self {
+            clock_offset: 0.0,
+            max_gap: Duration::from_secs(5),
+        }
+    }
+}
+
+impl HeartRateTrack {
+    fn new(session: &Session, options: &MergeOptions) -> Self {
+        let offset_ms = (options.clock_offset * 1000.0).round() as i64;
+        let mut samples = session
+            .samples
+            .iter()
+            // Without sensor contact the heart rate is meaningless
+            .filter(|v| v.hrm.heart_rate > 0 && !v.hrm.contact_lost())
+            .map(|v| (unix_ms(v.wall_clock) + offset_ms, v.hrm.heart_rate))
+            .collect::<Vec<_>>();
+        samples.sort_by_key(|v| v.0);
+        Self {
+            samples,
+            max_gap_ms: options.max_gap.as_millis() as i64,
+        }
+    }
+
+    /// Heart rate at `time`, `None` if there is no sample near it.
+    fn at(&self, time: SystemTime) -> Option<u8> {
+        let t = unix_ms(time);
+        let i = self.samples.partition_point(|v| v.0 < t);
+        let next = self.samples.get(i);
+        let prev = i.checked_sub(1).and_then(|i| self.samples.get(i));
+        let heart_rate = match (prev, next) {
+            (_, Some(&(nt, hr))) if nt == t => hr as f64,
+            (Some(&(pt, phr)), Some(&(nt, nhr))) if nt - pt <= self.max_gap_ms => {
+                let ratio = (t - pt) as f64 / (nt - pt) as f64;
+                phr as f64 + (nhr as f64 - phr as f64) * ratio
+            }
+            _ => {
+                let tolerance = NEAREST_TOLERANCE.as_millis() as i64;
+                let (_, hr) = [prev, next]
+                    .into_iter()
+                    .flatten()
+                    .filter(|v| (v.0 - t).abs() <= tolerance)
+                    .min_by_key(|v| (v.0 - t).abs())?;
+                *hr as f64
+            }
+        };
+        // Valid range of all formats
+        Some(heart_rate.round().clamp(1.0, 254.0) as u8)
+    }
+}
+
+/// Merge the session into the activity file (GPX, TCX or FIT, by extension), and save as
+/// `<name>-hr.<ext>` next to the activity. Return the output path and the number of points got
+/// heart rate.
+pub fn merge_file(
+    activity: &Path,
+    session: &Path,
+    options: &MergeOptions,
+) -> io::Result<(PathBuf, usize)> {
+    let track = HeartRateTrack::new(&Session::load(session)?, options);
+    let extension = activity
+        .extension()
+        .map(|v| v.to_string_lossy().to_ascii_lowercase())
+        .unwrap_or_default();
+    let (merged, count) = match extension.as_str() {
+        "gpx" => {
+            let (xml, count) = merge_gpx(&fs::read_to_string(activity)?, &track);
+            (xml.into_bytes(), count)
+        }
+        "tcx" => {
+            let (xml, count) = merge_tcx(&fs::read_to_string(activity)?, &track);
+            (xml.into_bytes(), count)
+        }
+        "fit" => merge_fit(&fs::read(activity)?, &track)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?,
+        _ => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "unsupported activity file, expect GPX, TCX or FIT",
+            ));
+        }
+    };
+
+    let stem = activity
+        .file_stem()
+        .map_or_else(String::new, |v| v.to_string_lossy().into_owned());
+    let output = activity.with_file_name(format!("{stem}-hr.{extension}"));
+    fs::write(&output, merged)?;
+    Ok((output, count))
+}
+
+/// Set `hr` of Garmin TrackPointExtension in every `trkpt`
+fn merge_gpx(xml: &str, track: &HeartRateTrack) -> (String, usize) {
+    let mut count = 0;
+    let mut xml = map_elements(xml, "trkpt", |point| {
+        let Some(hr) = element_text(point, "time")
+            .and_then(UtcDateTime::parse)
+            .and_then(|t| track.at(t))
+        else {
+            return point.to_string();
+        };
+        count += 1;
+        let point = expand_empty_element(point, "extensions");
+
+        // The prefix of TrackPointExtension varies between software
+        if let Some(prefix) = element_prefix(&point, "TrackPointExtension") {
+            let tag = format!("{prefix}TrackPointExtension");
+            return map_elements(&point, &tag, |ext| {
+                let ext = expand_empty_element(ext, &tag);
+                let ext = remove_elements(&ext, &format!("{prefix}hr"));
+                let hr = format!("<{prefix}hr>{hr}</{prefix}hr>");
+                // `hr` goes before `cad` and `Extensions`
+                let close = format!("</{prefix}TrackPointExtension>");
+                let position = [
+                    format!("<{prefix}cad"),
+                    format!("<{prefix}Extensions"),
+                    close,
+                ]
+                .iter()
+                .filter_map(|v| ext.find(v.as_str()))
+                .min()
+                .unwrap_or(ext.len());
+                format!("{}{hr}{}", &ext[..position], &ext[position..])
+            });
+        }
+        let ext = format!(
+            "<gpxtpx:TrackPointExtension><gpxtpx:hr>{hr}</gpxtpx:hr></gpxtpx:TrackPointExtension>"
+        );
+        if let Some(position) = point.find("</extensions>") {
+            format!("{}{ext}{}", &point[..position], &point[position..])
+        } else {
+            let position = point.rfind("</trkpt>").unwrap_or(point.len());
+            let ext = format!("<extensions>{ext}</extensions>");
+            format!("{}{ext}{}", &point[..position], &point[position..])
+        }
+    });
+
+    if xml.contains("<gpxtpx:")
+        && !xml.contains("xmlns:gpxtpx=")
+        && let Some((start, _)) = find_element(&xml, "gpx")
+    {
+        let position = start + "<gpx".len();
+        xml.insert_str(position, &format!(r#" xmlns:gpxtpx="{GPX_TPX_NAMESPACE}""#));
+    }
+    (xml, count)
+}
+
+/// Set `HeartRateBpm` of every `Trackpoint`, and update heart rate summaries of laps.
+fn merge_tcx(xml: &str, track: &HeartRateTrack) -> (String, usize) {
+    let mut count = 0;
+    let xml = map_elements(xml, "Trackpoint", |point| {
+        let Some(hr) = element_text(point, "Time")
+            .and_then(UtcDateTime::parse)
+            .and_then(|t| track.at(t))
+        else {
+            return point.to_string();
+        };
+        count += 1;
+
+        let point = remove_elements(point, "HeartRateBpm");
+        // `HeartRateBpm` goes before `Cadence`, `SensorState` and `Extensions`
+        let position = ["<Cadence", "<SensorState", "<Extensions", "</Trackpoint>"]
+            .iter()
+            .filter_map(|v| point.find(v))
+            .min()
+            .unwrap_or(point.len());
+        format!(
+            "{}<HeartRateBpm><Value>{hr}</Value></HeartRateBpm>{}",
+            &point[..position],
+            &point[position..]
+        )
+    });
+
+    let xml = map_elements(&xml, "Lap", |lap| {
+        let heart_rates = elements(lap, "Trackpoint")
+            .filter_map(|v| element_text(v, "HeartRateBpm"))
+            .filter_map(|v| element_text(v, "Value")?.trim().parse::<u32>().ok())
+            .collect::<Vec<_>>();
+        let Some(&max) = heart_rates.iter().max() else {
+            return lap.to_string();
+        };
+        let average = heart_rates.iter().sum::<u32>() / heart_rates.len() as u32;
+        let lap = remove_elements(lap, "AverageHeartRateBpm");
+        let lap = remove_elements(&lap, "MaximumHeartRateBpm");
+        // Heart rate summaries go before `Intensity`
+        let Some(position) = lap.find("<Intensity") else {
+            return lap;
+        };
+        format!(
+            "{}<AverageHeartRateBpm><Value>{average}</Value></AverageHeartRateBpm>\
+             <MaximumHeartRateBpm><Value>{max}</Value></MaximumHeartRateBpm>{}",
+            &lap[..position],
+            &lap[position..]
+        )
+    });
+    (xml, count)
+}
+
+/// Set `heart_rate` of every `record`, and update heart rate summaries of laps and sessions.
+fn merge_fit(file: &[u8], track: &HeartRateTrack) -> Result<(Vec<u8>, usize), fit::FitError> {
+    const RECORD_HEART_RATE: u8 = 3;
+    const START_TIME: u8 = 2;
+
+    let mut messages = fit::decode(file)?;
+    let mut merged = Vec::new();
+    for message in messages
+        .iter_mut()
+        .filter(|v| v.global == fit_global::RECORD)
+    {
+        let Some(timestamp) = message.timestamp() else {
+            continue;
+        };
+        let time = SystemTime::UNIX_EPOCH + Duration::from_secs(fit::to_unix_secs(timestamp));
+        if let Some(hr) = track.at(time) {
+            message.set_field(Field::uint8(RECORD_HEART_RATE, hr));
+            merged.push((timestamp, hr));
+        }
+    }
+
+    let summarize = |message: &mut Message, average_field: u8, max_field: u8| {
+        let (Some(start), Some(end)) = (
+            message.field(START_TIME).and_then(Field::as_u32),
+            message.timestamp(),
+        ) else {
+            return;
+        };
+        let heart_rates = merged
+            .iter()
+            .filter(|v| (start..=end).contains(&v.0))
+            .map(|v| v.1 as u32)
+            .collect::<Vec<_>>();
+        if let Some(&max) = heart_rates.iter().max() {
+            let average = heart_rates.iter().sum::<u32>() / heart_rates.len() as u32;
+            message.set_field(Field::uint8(average_field, average as u8));
+            message.set_field(Field::uint8(max_field, max as u8));
+        }
+    };
+    for message in &mut messages {
+        match message.global {
+            fit_global::LAP => summarize(message, 15, 16),
+            fit_global::SESSION => summarize(message, 16, 17),
+            _ => {}
+        }
+    }
+    Ok((fit::encode(&messages)?, merged.len()))
+}
+
+fn unix_ms(time: SystemTime) -> i64 {
+    time.duration_since(SystemTime::UNIX_EPOCH)
+        .map_or(0, |v| v.as_millis() as i64)
+}
+
+/// Replace every `<tag ...>...</tag>` element (including the tags) with the result of `f`.
+fn map_elements(xml: &str, tag: &str, mut f: impl FnMut(&str) -> String) -> String {
+    let mut output = String::with_capacity(xml.len());
+    let mut rest = xml;
+    while let Some((start, end)) = find_element(rest, tag) {
+        output.push_str(&rest[..start]);
+        output.push_str(&f(&rest[start..end]));
+        rest = &rest[end..];
+    }
+    output.push_str(rest);
+    output
+}
+
+fn remove_elements(xml: &str, tag: &str) -> String {
+    map_elements(xml, tag, |_| String::new())
+}
+
+/// Every `<tag ...>...</tag>` or `<tag/>` element
+fn elements<'a>(xml: &'a str, tag: &str) -> impl Iterator<Item = &'a str> {
+    let mut rest = xml;
+    std::iter::from_fn(move || {
+        let (start, end) = find_element(rest, tag)?;
+        let element = &rest[start..end];
+        rest = &rest[end..];
+        Some(element)
+    })
+}
+
+/// Rewrite every empty `<tag .../>` element as `<tag ...></tag>`, so content can be inserted.
+fn expand_empty_element(xml: &str, tag: &str) -> String {
+    map_elements(xml, tag, |element| match element.strip_suffix("/>") {
+        Some(open) => format!("{}></{tag}>", open.trim_end()),
+        None => element.to_string(),
+    })
+}
+
+/// Byte range of the first `<tag ...>...</tag>` or `<tag/>` element
+fn find_element(xml: &str, tag: &str) -> Option<(usize, usize)> {
+    let open = format!("<{tag}");
+    let close = format!("</{tag}>");
+    let mut offset = 0;
+    loop {
+        let start = offset + xml[offset..].find(&open)?;
+        let after = start + open.len();
+        // Not a longer tag name with the same prefix
+        match xml[after..].chars().next()? {
+            '>' | '/' => {}
+            c if c.is_whitespace() => {}
+            _ => {
+                offset = after;
+                continue;
+            }
+        }
+        let tag_end = after + xml[after..].find('>')?;
+        if xml[..tag_end].ends_with('/') {
+            return Some((start, tag_end + 1));
+        }
+        let end = tag_end + xml[tag_end..].find(&close)? + close.len();
+        return Some((start, end));
+    }
+}
+
+/// Text of the first `<tag>` element
+fn element_text<'a>(xml: &'a str, tag: &str) -> Option<&'a str> {
+    let (start, end) = find_element(xml, tag)?;
+    let element = &xml[start..end];
+    let content_start = element.find('>')? + 1;
+    let content_end = element.rfind("</")?;
+    element.get(content_start..content_end)
+}
+
+/// Namespace prefix (including `:`) of the first element named `name`, `Some("")` if it has no
+/// prefix.
+fn element_prefix<'a>(xml: &'a str, name: &str) -> Option<&'a str> {
+    xml.match_indices('<').find_map(|(i, _)| {
+        let tag = &xml[i + 1..];
+        let tag = &tag[..tag.find(|c: char| c.is_whitespace() || c == '>' || c == '/')?];
+        let prefix = tag.strip_suffix(name)?;
+        (prefix.is_empty() || prefix.ends_with(':')).then_some(prefix)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 2025-01-02T03:04:05Z, unit: ms
+    const START: i64 = 1_735_787_045_000;
+
+    const GPX_WITHOUT_EXTENSION: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<gpx version="1.1" creator="test" xmlns="http://www.topografix.com/GPX/1/1">
+  <trk><trkseg>
+    <trkpt lat="1" lon="2"><ele>10</ele><time>2025-01-02T03:04:05Z</time></trkpt>
+    <trkpt lat="1" lon="2"><time>2025-01-02T03:04:06Z</time><extensions/></trkpt>
+    <trkpt lat="1" lon="2"><time>2025-01-02T04:00:00Z</time></trkpt>
+  </trkseg></trk>
+</gpx>
+"#;
+
+    const GPX_WITH_EXTENSION: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<gpx version="1.1" creator="test" xmlns="http://www.topografix.com/GPX/1/1" xmlns:ns3="http://www.garmin.com/xmlschemas/TrackPointExtension/v1">
+  <trk><trkseg>
+    <trkpt lat="1" lon="2"><time>2025-01-02T03:04:05Z</time><extensions><ns3:TrackPointExtension><ns3:hr>60</ns3:hr><ns3:cad>85</ns3:cad></ns3:TrackPointExtension></extensions></trkpt>
+    <trkpt lat="1" lon="2"><time>2025-01-02T03:04:06Z</time><extensions><ns3:TrackPointExtension/></extensions></trkpt>
+    <trkpt lat="1" lon="2"><time>2025-01-02T03:04:07Z</time><extensions><ns3:TrackPointExtension><ns3:atemp>20</ns3:atemp></ns3:TrackPointExtension></extensions></trkpt>
+  </trkseg></trk>
+</gpx>
+"#;
+
+    const TCX: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<TrainingCenterDatabase xmlns="http://www.garmin.com/xmlschemas/TrainingCenterDatabase/v2">
+  <Activities><Activity Sport="Biking"><Id>2025-01-02T03:04:05Z</Id>
+    <Lap StartTime="2025-01-02T03:04:05Z">
+      <TotalTimeSeconds>2</TotalTimeSeconds><DistanceMeters>20</DistanceMeters><Calories>1</Calories>
+      <AverageHeartRateBpm><Value>60</Value></AverageHeartRateBpm>
+      <MaximumHeartRateBpm><Value>60</Value></MaximumHeartRateBpm>
+      <Intensity>Active</Intensity><TriggerMethod>Manual</TriggerMethod>
+      <Track>
+        <Trackpoint><Time>2025-01-02T03:04:05Z</Time><HeartRateBpm><Value>60</Value></HeartRateBpm><Cadence>80</Cadence></Trackpoint>
+        <Trackpoint><Time>2025-01-02T03:04:06Z</Time><DistanceMeters>10</DistanceMeters></Trackpoint>
+        <Trackpoint><Time>2025-01-02T03:04:07Z</Time><SensorState>Present</SensorState></Trackpoint>
+      </Track>
+    </Lap>
+    <Lap StartTime="2025-01-02T05:00:00Z">
+      <TotalTimeSeconds>1</TotalTimeSeconds><DistanceMeters>0</DistanceMeters><Calories>0</Calories>
+      <Intensity>Active</Intensity><TriggerMethod>Manual</TriggerMethod>
+      <Track><Trackpoint><Time>2025-01-02T05:00:00Z</Time></Trackpoint></Track>
+    </Lap>
+  </Activity></Activities>
+</TrainingCenterDatabase>
+"#;
+
+    /// 100 bpm at the start, rising 10 bpm per second
+    fn track() -> HeartRateTrack {
+        HeartRateTrack {
+            samples: (0..3)
+                .map(|i| (START + i * 1000, 100 + i as u16 * 10))
+                .collect(),
+            max_gap_ms: 5000,
+        }
+    }
+
+    #[test]
+    fn gpx_without_extension() {
+        let (xml, count) = merge_gpx(GPX_WITHOUT_EXTENSION, &track());
+        assert_eq!(count, 2);
+        assert!(xml.contains(concat!(
+            r#"<ele>10</ele><time>2025-01-02T03:04:05Z</time><extensions>"#,
+            r#"<gpxtpx:TrackPointExtension><gpxtpx:hr>100</gpxtpx:hr></gpxtpx:TrackPointExtension>"#,
+            r#"</extensions></trkpt>"#
+        )));
+        assert!(xml.contains(concat!(
+            r#"<time>2025-01-02T03:04:06Z</time><extensions>"#,
+            r#"<gpxtpx:TrackPointExtension><gpxtpx:hr>110</gpxtpx:hr></gpxtpx:TrackPointExtension>"#,
+            r#"</extensions></trkpt>"#
+        )));
+        assert!(!xml.contains("<extensions/>"));
+        assert!(xml.contains(r#"<time>2025-01-02T04:00:00Z</time></trkpt>"#));
+        assert!(xml.contains(&format!(
+            r#"<gpx xmlns:gpxtpx="{GPX_TPX_NAMESPACE}" version="1.1""#
+        )));
+    }
+
+    #[test]
+    fn gpx_with_extension() {
+        let (xml, count) = merge_gpx(GPX_WITH_EXTENSION, &track());
+        assert_eq!(count, 3);
+        // Replaced, before `cad`
+        assert!(xml.contains(concat!(
+            r#"<ns3:TrackPointExtension><ns3:hr>100</ns3:hr><ns3:cad>85</ns3:cad>"#,
+            r#"</ns3:TrackPointExtension>"#
+        )));
+        // Self-closing extension is expanded
+        assert!(xml.contains(
+            r#"<ns3:TrackPointExtension><ns3:hr>110</ns3:hr></ns3:TrackPointExtension>"#
+        ));
+        assert!(xml.contains(concat!(
+            r#"<ns3:TrackPointExtension><ns3:atemp>20</ns3:atemp><ns3:hr>120</ns3:hr>"#,
+            r#"</ns3:TrackPointExtension>"#
+        )));
+        // The existing prefix is used, no new namespace is declared
+        assert!(!xml.contains("gpxtpx"));
+    }
+
+    #[test]
+    fn gpx_with_default_namespace_extension() {
+        let gpx = GPX_WITH_EXTENSION.replace("ns3:", "").replace(":ns3=", "=");
+        let (xml, count) = merge_gpx(&gpx, &track());
+        assert_eq!(count, 3);
+        assert!(
+            xml.contains("<TrackPointExtension><hr>100</hr><cad>85</cad></TrackPointExtension>")
+        );
+        assert!(xml.contains("<TrackPointExtension><hr>110</hr></TrackPointExtension>"));
+    }
+
+    #[test]
+    fn tcx() {
+        let (xml, count) = merge_tcx(TCX, &track());
+        assert_eq!(count, 3);
+        let trackpoints = elements(&xml, "Trackpoint").collect::<Vec<_>>();
+        assert_eq!(
+            trackpoints[..3],
+            [
+                concat!(
+                    "<Trackpoint><Time>2025-01-02T03:04:05Z</Time>",
+                    "<HeartRateBpm><Value>100</Value></HeartRateBpm><Cadence>80</Cadence></Trackpoint>"
+                ),
+                concat!(
+                    "<Trackpoint><Time>2025-01-02T03:04:06Z</Time><DistanceMeters>10</DistanceMeters>",
+                    "<HeartRateBpm><Value>110</Value></HeartRateBpm></Trackpoint>"
+                ),
+                concat!(
+                    "<Trackpoint><Time>2025-01-02T03:04:07Z</Time>",
+                    "<HeartRateBpm><Value>120</Value></HeartRateBpm>",
+                    "<SensorState>Present</SensorState></Trackpoint>"
+                ),
+            ]
+        );
+        assert_eq!(
+            trackpoints[3],
+            "<Trackpoint><Time>2025-01-02T05:00:00Z</Time></Trackpoint>"
+        );
+
+        let laps = elements(&xml, "Lap").collect::<Vec<_>>();
+        assert!(laps[0].find("<Calories>") < laps[0].find("<AverageHeartRateBpm>"));
+        assert!(laps[0].contains(concat!(
+            "<AverageHeartRateBpm><Value>110</Value></AverageHeartRateBpm>",
+            "<MaximumHeartRateBpm><Value>120</Value></MaximumHeartRateBpm>",
+            "<Intensity>"
+        )));
+        assert_eq!(laps[0].matches("AverageHeartRateBpm>").count(), 2);
+        // No heart rate in the lap, the summaries are not added
+        assert!(!laps[1].contains("HeartRateBpm"));
+    }
+
+    #[test]
+    fn fit() {
+        let start = fit::from_unix_secs(START as u64 / 1000);
+        let record = |offset: u32| {
+            Message::new(fit_global::RECORD)
+                .with(Field::uint32(fit::TIMESTAMP_FIELD, start + offset))
+                .with(Field::uint8(3, 60))
+        };
+        let summary = |global: u16| {
+            Message::new(global)
+                .with(Field::uint32(fit::TIMESTAMP_FIELD, start + 2))
+                .with(Field::uint32(2, start))
+        };
+        let file = fit::encode(&[
+            record(0),
+            record(1),
+            record(2),
+            record(3600),
+            summary(fit_global::LAP),
+            summary(fit_global::SESSION),
+        ])
+        .unwrap();
+
+        let (merged, count) = merge_fit(&file, &track()).unwrap();
+        assert_eq!(count, 3);
+        let messages = fit::decode(&merged).unwrap();
+        let value = |message: &Message, number: u8| message.field(number).and_then(Field::as_u32);
+        let heart_rates = messages
+            .iter()
+            .filter(|v| v.global == fit_global::RECORD)
+            .map(|v| value(v, 3))
+            .collect::<Vec<_>>();
+        assert_eq!(heart_rates, [Some(100), Some(110), Some(120), Some(60)]);
+
+        let lap = messages
+            .iter()
+            .find(|v| v.global == fit_global::LAP)
+            .unwrap();
+        assert_eq!((value(lap, 15), value(lap, 16)), (Some(110), Some(120)));
+        let session = messages
+            .iter()
+            .find(|v| v.global == fit_global::SESSION)
+            .unwrap();
+        assert_eq!(
+            (value(session, 16), value(session, 17)),
+            (Some(110), Some(120))
+        );
+    }
+
+    #[test]
+    fn interpolate_within_max_gap() {
+        let track = HeartRateTrack {
+            samples: vec![(START, 100), (START + 4000, 140), (START + 20_000, 60)],
+            max_gap_ms: 5000,
+        };
+        let at = |ms: i64| track.at(SystemTime::UNIX_EPOCH + Duration::from_millis(ms as u64));
+        assert_eq!(at(START + 1000), Some(110));
+        assert_eq!(at(START + 4500), Some(140));
+        assert_eq!(at(START + 10_000), None);
+        assert_eq!(at(START - 1000), Some(100));
+        assert_eq!(at(START - 1001), None);
+    }
+}
@@ -0,0 +1,154 @@
+//! Record heart rate measurements of a connection into a session file, see [`crate::session`].
+
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Instant, SystemTime};
+
+use log::warn;
+
+use crate::alarm::AlarmEvent;
+use crate::datetime::UtcDateTime;
+use crate::history::{self, SessionMeta};
+use crate::hrm::HeartRateMeasurement;
+use crate::session::{CSV_HEADER, Sample};
+
+/// Maximum numeric suffix tried when the session file name is already taken
+const MAX_NAME_SUFFIX: u32 = 100;
+
+#[derive(Debug)]
+pub struct Recorder {
+    path: PathBuf,
+    meta: SessionMeta,
+    writer: BufWriter<File>,
+    started: Instant,
+    /// Number of recorded measurements
+    samples: usize,
+}
+
+impl Recorder {
+    /// Create a new session file in `dir` for `device`, the directory is created if it doesn't
+    /// exist.
+    pub fn create(dir: &Path, device: String) -> io::Result<Self> {
+        fs::create_dir_all(dir)?;
+        let stamp = UtcDateTime::from(SystemTime::now()).file_stamp();
+        let (name, path, file) = create_session_file(dir, &stamp)?;
+        let mut writer = BufWriter::new(file);
+        writeln!(writer, "{CSV_HEADER}")?;
+        writer.flush()?;
+        let meta = SessionMeta {
+            name,
+            device: Some(device),
+            ..Default::default()
+        };
+        meta.save(&path)?;
+        Ok(Self {
+            path,
+            meta,
+            writer,
+            started: Instant::now(),
+            samples: 0,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Append a measurement, it is flushed immediately so nothing is lost if the app crashes.
+    pub fn record(&mut self, hrm: &HeartRateMeasurement) -> io::Result<()> {
+        let sample = Sample {
+            wall_clock: SystemTime::now(),
+            monotonic: self.started.elapsed(),
+            hrm: hrm.clone(),
+        };
+        writeln!(self.writer, "{}", sample.to_csv_row())?;
+        self.samples += 1;
+        self.writer.flush()
+    }
+
+    /// Save the alarms raised so far into the sidecar of the session file.
+    pub fn save_alarms(&mut self, alarms: &[AlarmEvent]) -> io::Result<()> {
+        self.meta.alarms = alarms.to_vec();
+        self.meta.save(&self.path)
+    }
+}
+
+/// A session without any measurement is useless, it is deleted instead of being left on disk.
+impl Drop for Recorder {
+    fn drop(&mut self) {
+        if self.samples == 0
+            && let Err(e) = history::delete_session(&self.path)
+        {
+            warn!(
+                "Failed to delete empty session '{}': {e}",
+                self.path.display()
+            );
+        }
+    }
+}
+
+/// Create a new session file named after `stamp`, a numeric suffix is appended if the name is
+/// already taken (e.g. reconnected within the same second). Return the name, path and the file.
+fn create_session_file(dir: &Path, stamp: &str) -> io::Result<(String, PathBuf, File)> {
+    for suffix in 1..=MAX_NAME_SUFFIX {
+        let name = match suffix {
+            1 => format!("session-{stamp}"),
+            n => format!("session-{stamp}-{n}"),
+        };
+        let path = dir.join(format!("{name}.csv"));
+        match File::create_new(&path) {
+            Ok(file) => return Ok((name, path, file)),
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e),
+        }
+    }
+    Err(io::Error::new(
+        io::ErrorKind::AlreadyExists,
+        format!("too many sessions recorded at {stamp}"),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn suffix_taken_names() {
+        let dir = std::env::temp_dir().join(format!("hr-view-recorder-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        let names = (0..3)
+            .map(|_| create_session_file(&dir, "20250101-000000").unwrap().0)
+            .collect::<Vec<_>>();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(
+            names,
+            [
+                "session-20250101-000000",
+                "session-20250101-000000-2",
+                "session-20250101-000000-3"
+            ]
+        );
+    }
+
+    #[test]
+    fn delete_empty_session() {
+        let dir = std::env::temp_dir().join(format!("hr-view-empty-{}", std::process::id()));
+        let empty = Recorder::create(&dir, "device".to_string()).unwrap();
+        let empty_path = empty.path().to_path_buf();
+        let mut recorded = Recorder::create(&dir, "device".to_string()).unwrap();
+        recorded
+            .record(&HeartRateMeasurement::parse(&[0, 80]).unwrap())
+            .unwrap();
+        let recorded_path = recorded.path().to_path_buf();
+        assert!(empty_path.exists());
+
+        drop(empty);
+        drop(recorded);
+        let exists =
+            [&empty_path, &recorded_path].map(|v| (v.exists(), v.with_extension("json").exists()));
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(exists, [(false, false), (true, true)]);
+    }
+}
@@ -0,0 +1,227 @@
+//! Replay a recorded session as a virtual device
+
+use std::fmt::Display;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use iced::futures::{StreamExt, stream};
+
+use crate::session::{Sample, Session};
+use crate::source::{
+    BoxFuture, Device, DeviceId, EventSender, EventStream, HeartRateDevice, HeartRateSource,
+    HeartRateStream, Result, SourceEvent, SourceState,
+};
+
+/// How often the playback checks whether next sample is due
+const TICK: Duration = Duration::from_millis(50);
+
+/// Replay speed multiplier
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Speed(pub u8);
+
+/// A source always discovers exactly one device that replays the session
+#[derive(Debug, Clone)]
+pub struct ReplaySource {
+    name: String,
+    playback: Arc<Playback>,
+    events: EventSender,
+}
+
+#[derive(Debug, Clone)]
+struct ReplayDevice {
+    name: String,
+    events: EventSender,
+    connected: Arc<AtomicBool>,
+    playback: Arc<Playback>,
+}
+
+/// Playback controls of a replaying session, shared by the device and the app.
+#[derive(Debug)]
+pub struct Playback {
+    session: Arc<Session>,
+    state: Mutex<PlaybackState>,
+}
+
+#[derive(Debug)]
+struct PlaybackState {
+    position: Duration,
+    /// Index of the next sample to emit
+    next: usize,
+    paused: bool,
+    speed: Speed,
+    /// When the `position` advanced last time
+    last_tick: Instant,
+}
+
+impl Speed {
+    pub const ALL: &[Self] = &[Self(1), Self(2), Self(5), Self(10), Self(30)];
+}
+
+impl Display for Speed {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}x", self.0)
+    }
+}
+
+impl ReplaySource {
+    pub fn new(path: &Path, speed: Speed) -> std::io::Result<Self> {
+        let session = Session::load(path)?;
+        let name = path
+            .file_name()
+            .map_or_else(|| path.display().to_string(), |v| v.display().to_string());
+        Ok(Self {
+            name,
+            playback: Arc::new(Playback::new(Arc::new(session), speed)),
+            events: EventSender::default(),
+        })
+    }
+
+    /// Playback controls of the replayed session, shared by the discovered device.
+    pub fn playback(&self) -> Arc<Playback> {
+        self.playback.clone()
+    }
+}
+
+impl HeartRateSource for ReplaySource {
+    fn state(&self) -> BoxFuture<Result<SourceState>> {
+        Box::pin(async { Ok(SourceState::PoweredOn) })
+    }
+
+    fn events(&self) -> BoxFuture<Result<EventStream>> {
+        let stream = self.events.stream();
+        Box::pin(async move { Ok(stream) })
+    }
+
+    fn start_scan(&self) -> BoxFuture<Result<()>> {
+        self.events
+            .send(SourceEvent::DeviceDiscovered(Device::new(ReplayDevice {
+                name: self.name.clone(),
+                events: self.events.clone(),
+                connected: Arc::new(AtomicBool::new(false)),
+                playback: self.playback.clone(),
+            })));
+        Box::pin(async { Ok(()) })
+    }
+
+    fn stop_scan(&self) -> BoxFuture<Result<()>> {
+        Box::pin(async { Ok(()) })
+    }
+}
+
+impl HeartRateDevice for ReplayDevice {
+    fn id(&self) -> DeviceId {
+        DeviceId::new(format!("replay:{}", self.name))
+    }
+
+    fn name(&self) -> Option<String> {
+        Some(format!("Replay: {}", self.name))
+    }
+
+    fn connect(&self) -> BoxFuture<Result<()>> {
+        self.connected.store(true, Ordering::Relaxed);
+        Box::pin(async { Ok(()) })
+    }
+
+    fn disconnect(&self) -> BoxFuture<Result<()>> {
+        self.connected.store(false, Ordering::Relaxed);
+        self.events.send(SourceEvent::DeviceDisconnected(self.id()));
+        Box::pin(async { Ok(()) })
+    }
+
+    fn is_connected(&self) -> BoxFuture<Result<bool>> {
+        let connected = self.connected.load(Ordering::Relaxed);
+        Box::pin(async move { Ok(connected) })
+    }
+
+    fn subscribe(&self) -> BoxFuture<Result<HeartRateStream>> {
+        self.playback.seek(Duration::ZERO);
+        let device = self.clone();
+        let stream = stream::unfold(device, async |device| {
+            loop {
+                if !device.connected.load(Ordering::Relaxed) {
+                    return None;
+                }
+                if let Some(sample) = device.playback.advance() {
+                    return Some((Ok(sample.hrm), device));
+                }
+                tokio::time::sleep(TICK).await;
+            }
+        });
+        Box::pin(async move { Ok(stream.boxed() as HeartRateStream) })
+    }
+}
+
+impl Playback {
+    fn new(session: Arc<Session>, speed: Speed) -> Self {
+        Self {
+            session,
+            state: Mutex::new(PlaybackState {
+                position: Duration::ZERO,
+                next: 0,
+                paused: false,
+                speed,
+                last_tick: Instant::now(),
+            }),
+        }
+    }
+
+    pub fn duration(&self) -> Duration {
+        self.session.duration()
+    }
+
+    pub fn position(&self) -> Duration {
+        self.state.lock().unwrap().position
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.state.lock().unwrap().paused
+    }
+
+    pub fn set_paused(&self, paused: bool) {
+        let mut state = self.state.lock().unwrap();
+        state.paused = paused;
+        state.last_tick = Instant::now();
+    }
+
+    pub fn speed(&self) -> Speed {
+        self.state.lock().unwrap().speed
+    }
+
+    pub fn set_speed(&self, speed: Speed) {
+        self.state.lock().unwrap().speed = speed;
+    }
+
+    pub fn seek(&self, position: Duration) {
+        let mut state = self.state.lock().unwrap();
+        state.position = position.min(self.duration());
+        state.next = self
+            .session
+            .samples
+            .partition_point(|v| v.monotonic < state.position);
+        state.last_tick = Instant::now();
+    }
+
+    /// Advance the position by the elapsed time since last call, return the next sample if it is
+    /// due. The playback pauses at the end of session.
+    fn advance(&self) -> Option<Sample> {
+        let mut state = self.state.lock().unwrap();
+        let now = Instant::now();
+        if !state.paused {
+            let elapsed = now.duration_since(state.last_tick) * state.speed.0 as u32;
+            state.position = (state.position + elapsed).min(self.duration());
+        }
+        state.last_tick = now;
+
+        let sample = self.session.samples.get(state.next)?;
+        if state.paused || sample.monotonic > state.position {
+            return None;
+        }
+        state.next += 1;
+        if state.next == self.session.samples.len() {
+            state.paused = true;
+        }
+        Some(sample.clone())
+    }
+}
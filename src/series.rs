@@ -0,0 +1,67 @@
+//! Heart rate of the current connection over time, for charts
+
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Point {
+    /// Time since the series started
+    pub elapsed: Duration,
+    /// `None` if the heart rate is unreliable, e.g. sensor contact lost
+    pub heart_rate: Option<u16>,
+}
+
+#[derive(Debug, Clone)]
+pub struct HeartRateSeries {
+    started: Instant,
+    /// Ordered by `elapsed`
+    points: Vec<Point>,
+    /// RR-Intervals with the time since the series started they are received, ordered by time.
+    /// Unit of RR-Intervals: ms
+    rr_intervals: Vec<(Duration, f32)>,
+}
+
+impl HeartRateSeries {
+    pub fn push(
+        &mut self,
+        heart_rate: Option<u16>,
+        rr_intervals_ms: impl IntoIterator<Item = f32>,
+    ) {
+        let elapsed = self.elapsed();
+        self.points.push(Point {
+            elapsed,
+            heart_rate,
+        });
+        self.rr_intervals
+            .extend(rr_intervals_ms.into_iter().map(|v| (elapsed, v)));
+    }
+
+    /// Time since the series started
+    pub fn elapsed(&self) -> Duration {
+        self.started.elapsed()
+    }
+
+    pub fn points(&self) -> &[Point] {
+        &self.points
+    }
+
+    pub fn rr_intervals(&self) -> &[(Duration, f32)] {
+        &self.rr_intervals
+    }
+
+    /// Points within `window` before now
+    pub fn recent(&self, window: Duration) -> &[Point] {
+        let start = self.elapsed().saturating_sub(window);
+        let i = self.points.partition_point(|v| v.elapsed < start);
+        &self.points[i..]
+    }
+}
+
+impl Default for HeartRateSeries {
+    fn default() -> Self {
+        Self {
+            started: Instant::now(),
+            points: Vec::new(),
+            rr_intervals: Vec::new(),
+        }
+    }
+}
@@ -0,0 +1,208 @@
+//! Recorded session file
+//!
+//! A session is a CSV file, each row is a heart rate measurement:
+//!
+//! ```text
+//! wall_clock_ms,monotonic_ms,heart_rate,sensor_contact,energy_expended,rr_intervals_ms
+//! 1760000000000,0,72,true,12,812.500 820.313
+//! ```
+//!
+//! - `wall_clock_ms`: milliseconds since UNIX epoch
+//! - `monotonic_ms`: milliseconds since the session started
+//! - `sensor_contact` and `energy_expended`: empty if not supported. `sensor_contact` is `false`
+//!   for samples measured without skin contact, their heart rate is unreliable.
+//! - `rr_intervals_ms`: space separated, may be empty
+
+use std::fs;
+use std::io;
+use std::num::NonZeroU16;
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+
+use crate::hrm::HeartRateMeasurement;
+
+pub const CSV_HEADER: &str =
+    "wall_clock_ms,monotonic_ms,heart_rate,sensor_contact,energy_expended,rr_intervals_ms";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sample {
+    pub wall_clock: SystemTime,
+    /// Time since the session started
+    pub monotonic: Duration,
+    pub hrm: HeartRateMeasurement,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Session {
+    /// Ordered by `monotonic`
+    pub samples: Vec<Sample>,
+}
+
+impl Session {
+    pub fn load(path: &Path) -> io::Result<Self> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    pub fn parse(csv: &str) -> io::Result<Self> {
+        let mut samples = csv
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty() && line.trim() != CSV_HEADER)
+            .map(|(i, line)| {
+                Sample::parse(line).ok_or_else(|| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("invalid session data at line {}", i + 1),
+                    )
+                })
+            })
+            .collect::<io::Result<Vec<_>>>()?;
+        samples.sort_by_key(|v| v.monotonic);
+        Ok(Self { samples })
+    }
+
+    /// Time of the last sample since the session started
+    pub fn duration(&self) -> Duration {
+        self.samples.last().map_or(Duration::ZERO, |v| v.monotonic)
+    }
+}
+
+impl Sample {
+    /// Parse a CSV row, `None` if it is malformed.
+    pub fn parse(line: &str) -> Option<Self> {
+        let mut fields = line.trim().split(',');
+        let mut next = || fields.next().map(str::trim);
+
+        let wall_clock = SystemTime::UNIX_EPOCH + Duration::from_millis(next()?.parse().ok()?);
+        let monotonic = Duration::from_millis(next()?.parse().ok()?);
+        let heart_rate = next()?.parse().ok()?;
+        let sensor_contact = match next()? {
+            "" => None,
+            v => Some(v.parse().ok()?),
+        };
+        let energy_expended = match next()? {
+            "" => None,
+            v => Some(v.parse().ok()?),
+        };
+        let rr_intervals = next()
+            .unwrap_or_default()
+            .split_whitespace()
+            .map(|v| {
+                let ms = v.parse::<f32>().ok()?;
+                NonZeroU16::new((ms * 1024.0 / 1000.0).round() as u16)
+            })
+            .collect::<Option<Vec<_>>>()?;
+
+        Some(Self {
+            wall_clock,
+            monotonic,
+            hrm: HeartRateMeasurement {
+                heart_rate,
+                sensor_contact,
+                energy_expended,
+                rr_intervals,
+            },
+        })
+    }
+
+    /// Format as a CSV row, the inverse of [`parse`](Self::parse).
+    pub fn to_csv_row(&self) -> String {
+        let wall_clock = self
+            .wall_clock
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or(Duration::ZERO);
+        let optional = |v: Option<String>| v.unwrap_or_default();
+        let rr_intervals = self
+            .hrm
+            .rr_intervals_ms()
+            .map(|v| format!("{v:.3}"))
+            .collect::<Vec<_>>()
+            .join(" ");
+        format!(
+            "{},{},{},{},{},{}",
+            wall_clock.as_millis(),
+            self.monotonic.as_millis(),
+            self.hrm.heart_rate,
+            optional(self.hrm.sensor_contact.map(|v| v.to_string())),
+            optional(self.hrm.energy_expended.map(|v| v.to_string())),
+            rr_intervals
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(wall_clock_ms: u64, monotonic_ms: u64, hrm: HeartRateMeasurement) -> Sample {
+        Sample {
+            wall_clock: SystemTime::UNIX_EPOCH + Duration::from_millis(wall_clock_ms),
+            monotonic: Duration::from_millis(monotonic_ms),
+            hrm,
+        }
+    }
+
+    fn measurement(
+        sensor_contact: Option<bool>,
+        energy_expended: Option<u16>,
+        rr_intervals: &[u16],
+    ) -> HeartRateMeasurement {
+        HeartRateMeasurement {
+            heart_rate: 72,
+            sensor_contact,
+            energy_expended,
+            rr_intervals: rr_intervals
+                .iter()
+                .filter_map(|v| NonZeroU16::new(*v))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn round_trip_every_field_combination() {
+        let contacts = [None, Some(false), Some(true)];
+        let energies = [None, Some(0), Some(u16::MAX)];
+        let rr_intervals: [&[u16]; 3] = [&[], &[832], &[1, 800, u16::MAX]];
+        for contact in contacts {
+            for energy in energies {
+                for rr in rr_intervals {
+                    let sample = sample(1_760_000_000_123, 4_567, measurement(contact, energy, rr));
+                    let row = sample.to_csv_row();
+                    assert_eq!(Sample::parse(&row), Some(sample), "{row}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn round_trip_every_rr_interval() {
+        for rr in 1..=u16::MAX {
+            let sample = sample(0, 0, measurement(None, None, &[rr]));
+            assert_eq!(Sample::parse(&sample.to_csv_row()), Some(sample));
+        }
+    }
+
+    #[test]
+    fn timestamps_keep_milliseconds() {
+        let sample = sample(1_760_000_000_001, 3_600_999, measurement(None, None, &[]));
+        assert_eq!(sample.to_csv_row(), "1760000000001,3600999,72,,,");
+        assert_eq!(Sample::parse(&sample.to_csv_row()), Some(sample.clone()));
+
+        // Sub-millisecond precision is truncated
+        let mut precise = sample.clone();
+        precise.wall_clock += Duration::from_micros(999);
+        precise.monotonic += Duration::from_micros(999);
+        assert_eq!(Sample::parse(&precise.to_csv_row()), Some(sample));
+    }
+
+    #[test]
+    fn documented_row() {
+        let parsed = Sample::parse("1760000000000,0,72,true,12,812.500 820.313");
+        let expected = sample(
+            1_760_000_000_000,
+            0,
+            measurement(Some(true), Some(12), &[832, 840]),
+        );
+        assert_eq!(parsed, Some(expected));
+    }
+}
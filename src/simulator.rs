@@ -0,0 +1,513 @@
+//! Simulated heart rate device, for demo and development without Bluetooth.
+
+use std::num::NonZeroU16;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::time::{Duration, Instant, SystemTime};
+
+use iced::futures::{StreamExt, stream};
+
+use crate::hrm::HeartRateMeasurement;
+use crate::source::{
+    BatteryLevelStream, BodySensorLocation, BoxFuture, Device, DeviceId, DeviceInformation,
+    EventSender, EventStream, HeartRateDevice, HeartRateSource, HeartRateStream, Result,
+    SourceEvent, SourceState,
+};
+
+/// Interval between two notifications
+const NOTIFY_INTERVAL: Duration = Duration::from_secs(1);
+/// The battery level drops 1% per this duration
+const BATTERY_DRAIN_INTERVAL: Duration = Duration::from_mins(3);
+/// Accepted range of the simulated baseline and peak heart rate, unit: bpm
+const HEART_RATE_RANGE: std::ops::RangeInclusive<u16> = 20..=250;
+/// Range of simulated RR-Intervals, unit: ms. It covers [`HEART_RATE_RANGE`] with the jitter.
+const RR_RANGE: std::ops::RangeInclusive<f32> = 200.0..=3500.0;
+/// Maximum amplitude of the RR-Interval jitter, unit: ms
+const MAX_RR_JITTER: f32 = 200.0;
+
+/// Parameters of the simulated heart rate. The simulated workout repeats: rest for `phase`, ramp
+/// up for `ramp`, exercise for `phase`, then ramp down for `ramp`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SimulatorSettings {
+    /// Heart rate of rest, unit: bpm
+    pub baseline: u16,
+    /// Heart rate of exercise, unit: bpm
+    pub peak: u16,
+    pub ramp: Duration,
+    pub phase: Duration,
+    /// Amplitude of the random RR-Interval jitter, unit: ms
+    pub rr_jitter: f32,
+    /// Probability of losing sensor contact per notification
+    pub contact_loss: f32,
+    /// Probability of disconnecting per notification
+    pub disconnect: f32,
+}
+
+/// A source always discovers exactly one simulated device
+#[derive(Debug, Clone)]
+pub struct SimulatedSource {
+    settings: SimulatorSettings,
+    events: EventSender,
+}
+
+#[derive(Debug, Clone)]
+struct SimulatedDevice {
+    settings: SimulatorSettings,
+    events: EventSender,
+    connected: Arc<AtomicBool>,
+    /// Unit: J, reported in kJ
+    energy_expended: Arc<AtomicU32>,
+}
+
+/// State of the heart rate stream
+struct Simulation {
+    device: SimulatedDevice,
+    rng: Rng,
+    started: Instant,
+    /// Time of the last beat, relative to `started`
+    last_beat: Duration,
+    /// Remaining notifications without sensor contact
+    contact_lost: u32,
+}
+
+/// A xorshift pseudo random number generator, good enough for simulation
+struct Rng(u64);
+
+impl SimulatorSettings {
+    /// Apply a `--sim-<name>=<value>` command line option, return `false` if the option is not
+    /// recognized or the value is invalid.
+    pub fn apply_arg(&mut self, arg: &str) -> bool {
+        let Some((name, value)) = arg.strip_prefix("--sim-").and_then(|v| v.split_once('=')) else {
+            return false;
+        };
+        let secs = |v: &str| v.parse().ok().map(Duration::from_secs);
+        let probability = |v: &str| v.parse().ok().filter(|v| (0.0..=1.0).contains(v));
+        let heart_rate = |v: &str| v.parse().ok().filter(|v| HEART_RATE_RANGE.contains(v));
+        match name {
+            "baseline" => heart_rate(value).map(|v| self.baseline = v),
+            "peak" => heart_rate(value).map(|v| self.peak = v),
+            "ramp" => secs(value).map(|v| self.ramp = v),
+            "phase" => secs(value).map(|v| self.phase = v),
+            "jitter" => value
+                .parse()
+                .ok()
+                .filter(|v: &f32| v.is_finite())
+                .map(|v| self.rr_jitter = v.clamp(0.0, MAX_RR_JITTER)),
+            "contact-loss" => probability(value).map(|v| self.contact_loss = v),
+            "disconnect" => probability(value).map(|v| self.disconnect = v),
+            _ => None,
+        }
+        .is_some()
+    }
+
+    /// Target heart rate at `t` since the simulation started, unit: bpm
+    fn heart_rate_at(&self, t: Duration) -> f32 {
+        let (baseline, peak) = (self.baseline as f32, self.peak as f32);
+        let (ramp, phase) = (self.ramp.as_secs_f32(), self.phase.as_secs_f32());
+        let cycle = 2.0 * (ramp + phase);
+        if cycle <= 0.0 {
+            return baseline;
+        }
+
+        let t = t.as_secs_f32() % cycle;
+        let progress = if t < phase {
+            0.0
+        } else if t < phase + ramp {
+            (t - phase) / ramp
+        } else if t < 2.0 * phase + ramp {
+            1.0
+        } else {
+            1.0 - (t - 2.0 * phase - ramp) / ramp
+        };
+        baseline + (peak - baseline) * progress
+    }
+}
+
+impl Default for SimulatorSettings {
+    fn default() -> Self {
+        Self {
+            baseline: 65,
+            peak: 150,
+            ramp: Duration::from_mins(1),
+            phase: Duration::from_mins(2),
+            rr_jitter: 25.0,
+            contact_loss: 0.005,
+            disconnect: 0.0,
+        }
+    }
+}
+
+impl SimulatedSource {
+    pub fn new(settings: SimulatorSettings) -> Self {
+        Self {
+            settings,
+            events: EventSender::default(),
+        }
+    }
+}
+
+impl HeartRateSource for SimulatedSource {
+    fn state(&self) -> BoxFuture<Result<SourceState>> {
+        Box::pin(async { Ok(SourceState::PoweredOn) })
+    }
+
+    fn events(&self) -> BoxFuture<Result<EventStream>> {
+        let stream = self.events.stream();
+        Box::pin(async move { Ok(stream) })
+    }
+
+    fn start_scan(&self) -> BoxFuture<Result<()>> {
+        self.events.send(SourceEvent::DeviceDiscovered(Device::new(
+            SimulatedDevice {
+                settings: self.settings,
+                events: self.events.clone(),
+                connected: Arc::new(AtomicBool::new(false)),
+                energy_expended: Arc::new(AtomicU32::new(0)),
+            },
+        )));
+        Box::pin(async { Ok(()) })
+    }
+
+    fn stop_scan(&self) -> BoxFuture<Result<()>> {
+        Box::pin(async { Ok(()) })
+    }
+}
+
+impl HeartRateDevice for SimulatedDevice {
+    fn id(&self) -> DeviceId {
+        DeviceId::new("simulator")
+    }
+
+    fn name(&self) -> Option<String> {
+        Some("Simulated device".into())
+    }
+
+    fn connect(&self) -> BoxFuture<Result<()>> {
+        self.connected.store(true, Ordering::Relaxed);
+        Box::pin(async { Ok(()) })
+    }
+
+    fn disconnect(&self) -> BoxFuture<Result<()>> {
+        self.connected.store(false, Ordering::Relaxed);
+        self.events.send(SourceEvent::DeviceDisconnected(self.id()));
+        Box::pin(async { Ok(()) })
+    }
+
+    fn is_connected(&self) -> BoxFuture<Result<bool>> {
+        let connected = self.connected.load(Ordering::Relaxed);
+        Box::pin(async move { Ok(connected) })
+    }
+
+    fn subscribe(&self) -> BoxFuture<Result<HeartRateStream>> {
+        let simulation = Simulation {
+            device: self.clone(),
+            rng: Rng::new(),
+            started: Instant::now(),
+            last_beat: Duration::ZERO,
+            contact_lost: 0,
+        };
+        let stream = stream::unfold(simulation, async |mut simulation| {
+            tokio::time::sleep(NOTIFY_INTERVAL).await;
+            let hrm = simulation.next()?;
+            Some((Ok(hrm), simulation))
+        });
+        Box::pin(async move { Ok(stream.boxed() as HeartRateStream) })
+    }
+
+    fn read_body_sensor_location(&self) -> BoxFuture<Result<Option<BodySensorLocation>>> {
+        Box::pin(async { Ok(Some(BodySensorLocation::Chest)) })
+    }
+
+    fn subscribe_battery_level(&self) -> BoxFuture<Result<Option<(u8, BatteryLevelStream)>>> {
+        let connected = self.connected.clone();
+        let stream = stream::unfold(100u8, move |level| {
+            let connected = connected.clone();
+            async move {
+                tokio::time::sleep(BATTERY_DRAIN_INTERVAL).await;
+                let level = level.saturating_sub(1);
+                connected.load(Ordering::Relaxed).then_some((level, level))
+            }
+        });
+        Box::pin(async move { Ok(Some((100, stream.boxed() as BatteryLevelStream))) })
+    }
+
+    fn read_device_information(&self) -> BoxFuture<Result<DeviceInformation>> {
+        Box::pin(async {
+            Ok(DeviceInformation {
+                manufacturer_name: Some("hr-view".into()),
+                model_number: Some("Simulator".into()),
+                serial_number: None,
+                firmware_revision: Some(env!("CARGO_PKG_VERSION").into()),
+                hardware_revision: None,
+            })
+        })
+    }
+
+    fn supports_energy_reset(&self) -> bool {
+        true
+    }
+
+    fn reset_energy_expended(&self) -> BoxFuture<Result<()>> {
+        self.energy_expended.store(0, Ordering::Relaxed);
+        Box::pin(async { Ok(()) })
+    }
+}
+
+impl Simulation {
+    /// Generate the next notification, `None` if the device disconnected.
+    fn next(&mut self) -> Option<HeartRateMeasurement> {
+        self.next_at(self.started.elapsed())
+    }
+
+    /// Generate the notification at `now` since the simulation started
+    fn next_at(&mut self, now: Duration) -> Option<HeartRateMeasurement> {
+        let device = &self.device;
+        if !device.connected.load(Ordering::Relaxed) {
+            return None;
+        }
+        if self.rng.chance(device.settings.disconnect) {
+            device.connected.store(false, Ordering::Relaxed);
+            device
+                .events
+                .send(SourceEvent::DeviceDisconnected(device.id()));
+            return None;
+        }
+
+        let heart_rate = device.settings.heart_rate_at(now);
+        let mut rr_intervals = Vec::new();
+        loop {
+            let t = self.last_beat.as_secs_f32();
+            // Respiratory sinus arrhythmia, about 15 breaths per minute
+            let rsa = (t * std::f32::consts::TAU / 4.0).sin() * 20.0;
+            let rr =
+                60_000.0 / heart_rate.max(1.0) + rsa + self.rng.jitter(device.settings.rr_jitter);
+            let rr = Duration::from_secs_f32(rr.clamp(*RR_RANGE.start(), *RR_RANGE.end()) / 1000.0);
+            if self.last_beat + rr > now {
+                break;
+            }
+            self.last_beat += rr;
+            rr_intervals.extend(NonZeroU16::new((rr.as_secs_f32() * 1024.0) as u16));
+        }
+
+        // About 4 kJ per minute at 100 bpm
+        let joules = (heart_rate * 40.0 / 60.0 * NOTIFY_INTERVAL.as_secs_f32()) as u32;
+        let energy = device.energy_expended.fetch_add(joules, Ordering::Relaxed) + joules;
+
+        if self.contact_lost == 0 && self.rng.chance(device.settings.contact_loss) {
+            self.contact_lost = 3 + (self.rng.next_f32() * 10.0) as u32;
+        }
+        let contact = self.contact_lost == 0;
+        self.contact_lost = self.contact_lost.saturating_sub(1);
+
+        // Without contact, most devices still report a plausible but meaningless heart rate
+        let heart_rate = if contact {
+            heart_rate
+        } else {
+            let (baseline, peak) = (device.settings.baseline, device.settings.peak);
+            baseline as f32 + self.rng.next_f32() * peak.abs_diff(baseline) as f32
+        };
+        Some(HeartRateMeasurement {
+            heart_rate: heart_rate.round() as u16,
+            sensor_contact: Some(contact),
+            energy_expended: Some((energy / 1000).min(u16::MAX as u32) as u16),
+            rr_intervals: if contact { rr_intervals } else { Vec::new() },
+        })
+    }
+}
+
+impl Rng {
+    fn new() -> Self {
+        let seed = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map_or(0, |v| v.as_nanos() as u64);
+        Self(seed | 1)
+    }
+
+    fn next_f32(&mut self) -> f32 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    fn chance(&mut self, probability: f32) -> bool {
+        self.next_f32() < probability
+    }
+
+    /// Roughly normal distributed value in `-amplitude..amplitude`
+    fn jitter(&mut self, amplitude: f32) -> f32 {
+        let sum = (0..4).map(|_| self.next_f32()).sum::<f32>();
+        (sum / 2.0 - 1.0) * amplitude
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hrm::rr_to_ms;
+
+    fn simulation(settings: SimulatorSettings) -> Simulation {
+        Simulation {
+            device: SimulatedDevice {
+                settings,
+                events: EventSender::default(),
+                connected: Arc::new(AtomicBool::new(true)),
+                energy_expended: Arc::new(AtomicU32::new(0)),
+            },
+            rng: Rng(0x2545_F491_4F6C_DD1D),
+            started: Instant::now(),
+            last_beat: Duration::ZERO,
+            contact_lost: 0,
+        }
+    }
+
+    /// Constant heart rate without contact loss or disconnection
+    fn steady(heart_rate: u16, rr_jitter: f32) -> SimulatorSettings {
+        SimulatorSettings {
+            baseline: heart_rate,
+            peak: heart_rate,
+            rr_jitter,
+            contact_loss: 0.0,
+            disconnect: 0.0,
+            ..Default::default()
+        }
+    }
+
+    /// Measurements of the first `secs` seconds
+    fn run(settings: SimulatorSettings, secs: u64) -> Vec<HeartRateMeasurement> {
+        let mut simulation = simulation(settings);
+        (1..=secs)
+            .map(|v| simulation.next_at(Duration::from_secs(v)).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn rr_intervals_match_heart_rate() {
+        for heart_rate in [20, 65, 150, 250] {
+            let measurements = run(steady(heart_rate, 25.0), 120);
+            assert!(measurements.iter().all(|v| v.heart_rate == heart_rate));
+            let rr_intervals = measurements
+                .iter()
+                .flat_map(|v| v.rr_intervals.iter().map(|v| rr_to_ms(*v)))
+                .collect::<Vec<_>>();
+            // The beats fill the time
+            let total = rr_intervals.iter().sum::<u32>();
+            assert!(
+                (117_000..=120_000).contains(&total),
+                "{heart_rate} bpm: {total} ms"
+            );
+            let mean = total as f32 / rr_intervals.len() as f32;
+            let expected = 60_000.0 / heart_rate as f32;
+            assert!(
+                (mean - expected).abs() < expected * 0.05,
+                "{heart_rate} bpm: mean RR {mean} ms"
+            );
+        }
+    }
+
+    #[test]
+    fn heart_rate_follows_workout() {
+        let settings = SimulatorSettings {
+            ramp: Duration::from_secs(10),
+            phase: Duration::from_secs(20),
+            ..steady(60, 0.0)
+        };
+        let settings = SimulatorSettings {
+            peak: 160,
+            ..settings
+        };
+        let at = |secs| settings.heart_rate_at(Duration::from_secs(secs));
+        assert_eq!(at(0), 60.0);
+        assert_eq!(at(25), 110.0);
+        assert_eq!(at(35), 160.0);
+        assert_eq!(at(55), 110.0);
+        assert_eq!(at(60), 60.0);
+    }
+
+    #[test]
+    fn rr_intervals_bounded_with_extreme_jitter() {
+        let measurements = run(steady(250, MAX_RR_JITTER), 60)
+            .into_iter()
+            .chain(run(steady(20, MAX_RR_JITTER), 60));
+        for rr in measurements.flat_map(|v| v.rr_intervals) {
+            // Truncated to 1/1024 s
+            let rr = rr_to_ms(rr) as f32;
+            assert!(
+                (RR_RANGE.start() - 1.0..=*RR_RANGE.end()).contains(&rr),
+                "{rr}"
+            );
+        }
+    }
+
+    #[test]
+    fn contact_loss() {
+        let settings = SimulatorSettings {
+            contact_loss: 1.0,
+            ..steady(80, 25.0)
+        };
+        let measurements = run(settings, 10);
+        assert!(measurements.iter().any(|v| v.contact_lost()));
+        for v in measurements.iter().filter(|v| v.contact_lost()) {
+            assert!(v.rr_intervals.is_empty());
+        }
+    }
+
+    #[test]
+    fn parse_options() {
+        let mut settings = SimulatorSettings::default();
+        for arg in [
+            "--sim-baseline=50",
+            "--sim-peak=180",
+            "--sim-ramp=30",
+            "--sim-phase=90",
+            "--sim-jitter=12.5",
+            "--sim-contact-loss=0.1",
+            "--sim-disconnect=0",
+        ] {
+            assert!(settings.apply_arg(arg), "{arg}");
+        }
+        assert_eq!(
+            settings,
+            SimulatorSettings {
+                baseline: 50,
+                peak: 180,
+                ramp: Duration::from_secs(30),
+                phase: Duration::from_secs(90),
+                rr_jitter: 12.5,
+                contact_loss: 0.1,
+                disconnect: 0.0,
+            }
+        );
+    }
+
+    #[test]
+    fn reject_invalid_options() {
+        let mut settings = SimulatorSettings::default();
+        for arg in [
+            "--simulate",
+            "--sim-baseline",
+            "--sim-unknown=1",
+            "--sim-baseline=19",
+            "--sim-baseline=251",
+            "--sim-peak=0",
+            "--sim-peak=abc",
+            "--sim-ramp=-1",
+            "--sim-jitter=NaN",
+            "--sim-jitter=inf",
+            "--sim-contact-loss=1.5",
+            "--sim-disconnect=-0.1",
+        ] {
+            assert!(!settings.apply_arg(arg), "{arg}");
+        }
+        assert_eq!(settings, SimulatorSettings::default());
+    }
+
+    #[test]
+    fn clamp_jitter() {
+        let mut settings = SimulatorSettings::default();
+        assert!(settings.apply_arg("--sim-jitter=1e30"));
+        assert_eq!(settings.rr_jitter, MAX_RR_JITTER);
+        assert!(settings.apply_arg("--sim-jitter=-5"));
+        assert_eq!(settings.rr_jitter, 0.0);
+    }
+}
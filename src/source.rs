@@ -0,0 +1,298 @@
+//! Abstraction of heart rate data source
+//!
+//! A [`HeartRateSource`] discovers [`HeartRateDevice`]s (e.g. a Bluetooth adapter discovers
+//! _Heart Rate Service_ devices). The app only talks to these traits, so alternative sources and
+//! test doubles can be plugged in.
+//!
+//! Due to the requirements of [iced::Task](iced::Task) on futures, all returned `Future` have
+//! `static` lifetime.
+
+use std::fmt::{Debug, Display};
+use std::hash::{Hash, Hasher};
+use std::ops::Deref;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+
+use iced::futures::channel::mpsc;
+use iced::futures::{Stream, StreamExt};
+
+use crate::hrm::{HeartRateMeasurement, HrmParseError};
+
+pub type Error = Box<dyn std::error::Error + Send + Sync>;
+pub type Result<T> = std::result::Result<T, Error>;
+pub type BoxFuture<T> = Pin<Box<dyn Future<Output = T> + Send + 'static>>;
+
+/// Stream of heart rate updates, yield an `Err` when received invalid data
+pub type HeartRateStream =
+    Pin<Box<dyn Stream<Item = std::result::Result<HeartRateMeasurement, HrmParseError>> + Send>>;
+/// Stream of battery level (unit: %) updates
+pub type BatteryLevelStream = Pin<Box<dyn Stream<Item = u8> + Send>>;
+/// Stream of source events
+pub type EventStream = Pin<Box<dyn Stream<Item = SourceEvent> + Send>>;
+
+/// Identifier of a device, unique among devices of a source
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct DeviceId(String);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SourceState {
+    Unknown,
+    PoweredOn,
+    PoweredOff,
+}
+
+#[derive(Clone, Debug)]
+pub enum SourceEvent {
+    DeviceDiscovered(Device),
+    DeviceDisconnected(DeviceId),
+    StateUpdate(SourceState),
+}
+
+/// Value of _Body Sensor Location_ characteristic
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BodySensorLocation {
+    Other,
+    Chest,
+    Wrist,
+    Finger,
+    Hand,
+    EarLobe,
+    Foot,
+    Reserved(u8),
+}
+
+/// Strings of _Device Information Service_, `None` if the device doesn't expose it.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DeviceInformation {
+    pub manufacturer_name: Option<String>,
+    pub model_number: Option<String>,
+    pub serial_number: Option<String>,
+    pub firmware_revision: Option<String>,
+    pub hardware_revision: Option<String>,
+}
+
+/// Something can discover heart rate devices
+pub trait HeartRateSource: Debug + Send + Sync {
+    fn state(&self) -> BoxFuture<Result<SourceState>>;
+
+    /// Events of this source, the current state is not included.
+    fn events(&self) -> BoxFuture<Result<EventStream>>;
+
+    /// Start discovering devices, discovered devices are reported by
+    /// [`SourceEvent::DeviceDiscovered`].
+    fn start_scan(&self) -> BoxFuture<Result<()>>;
+
+    fn stop_scan(&self) -> BoxFuture<Result<()>>;
+}
+
+/// A device provides heart rate data. Except `connect`, all operations are only available after
+/// the device is connected.
+pub trait HeartRateDevice: Debug + Send + Sync {
+    fn id(&self) -> DeviceId;
+
+    fn name(&self) -> Option<String>;
+
+    fn connect(&self) -> BoxFuture<Result<()>>;
+
+    fn disconnect(&self) -> BoxFuture<Result<()>>;
+
+    fn is_connected(&self) -> BoxFuture<Result<bool>>;
+
+    /// Subscribe the heart rate measurements and return a stream of heart rate for heart rate
+    /// updates. This stream will be closed when the device disconnected.
+    fn subscribe(&self) -> BoxFuture<Result<HeartRateStream>>;
+
+    /// Return `Ok(None)` if the device doesn't provide it. Must be called after
+    /// [`subscribe`](Self::subscribe).
+    fn read_body_sensor_location(&self) -> BoxFuture<Result<Option<BodySensorLocation>>> {
+        Box::pin(async { Ok(None) })
+    }
+
+    /// Read the battery level (unit: %) and subscribe its updates, return `Ok(None)` if the device
+    /// doesn't provide it. Must be called after [`subscribe`](Self::subscribe).
+    fn subscribe_battery_level(&self) -> BoxFuture<Result<Option<(u8, BatteryLevelStream)>>> {
+        Box::pin(async { Ok(None) })
+    }
+
+    /// Must be called after [`subscribe`](Self::subscribe).
+    fn read_device_information(&self) -> BoxFuture<Result<DeviceInformation>> {
+        Box::pin(async { Ok(DeviceInformation::default()) })
+    }
+
+    /// Whether the accumulated _Energy Expended_ can be reset. Always `false` before
+    /// [`subscribe`](Self::subscribe).
+    fn supports_energy_reset(&self) -> bool {
+        false
+    }
+
+    fn reset_energy_expended(&self) -> BoxFuture<Result<()>> {
+        Box::pin(async { Err("resetting energy expended is not supported".into()) })
+    }
+}
+
+/// Helper for sources that produce events by themselves, all clones share the same channel.
+#[derive(Clone, Debug, Default)]
+pub struct EventSender(Arc<Mutex<Option<mpsc::UnboundedSender<SourceEvent>>>>);
+
+/// A source never discovers any device, in place of an unavailable one (e.g. there is no Bluetooth
+/// adapter). Its state is always [`SourceState::Unknown`].
+#[derive(Clone, Debug, Default)]
+pub struct UnavailableSource {
+    events: EventSender,
+}
+
+/// A discovered device, and the details read after it connected.
+#[derive(Clone, Debug)]
+pub struct Device {
+    inner: Arc<dyn HeartRateDevice>,
+    body_sensor_location: Option<BodySensorLocation>,
+    /// Unit: %
+    battery_level: Option<u8>,
+    device_information: Option<DeviceInformation>,
+}
+
+impl DeviceId {
+    pub fn new(id: impl Into<String>) -> Self {
+        Self(id.into())
+    }
+}
+
+impl EventSender {
+    /// Create a new event stream, the previous one will be closed.
+    pub fn stream(&self) -> EventStream {
+        let (tx, rx) = mpsc::unbounded();
+        *self.0.lock().unwrap() = Some(tx);
+        rx.boxed()
+    }
+
+    /// Send an event to current stream, do nothing if no stream.
+    pub fn send(&self, event: SourceEvent) {
+        if let Some(tx) = self.0.lock().unwrap().as_ref() {
+            let _ = tx.unbounded_send(event);
+        }
+    }
+}
+
+impl Device {
+    pub fn new(device: impl HeartRateDevice + 'static) -> Self {
+        Self {
+            inner: Arc::new(device),
+            body_sensor_location: None,
+            battery_level: None,
+            device_information: None,
+        }
+    }
+
+    /// Only available after the body sensor location is read.
+    pub fn body_sensor_location(&self) -> Option<BodySensorLocation> {
+        self.body_sensor_location
+    }
+
+    pub fn set_body_sensor_location(&mut self, location: Option<BodySensorLocation>) {
+        self.body_sensor_location = location;
+    }
+
+    /// Only available after the battery level is read, unit: %.
+    pub fn battery_level(&self) -> Option<u8> {
+        self.battery_level
+    }
+
+    pub fn set_battery_level(&mut self, level: Option<u8>) {
+        self.battery_level = level;
+    }
+
+    /// Only available after the device information is read.
+    pub fn device_information(&self) -> Option<&DeviceInformation> {
+        self.device_information.as_ref()
+    }
+
+    pub fn set_device_information(&mut self, info: Option<DeviceInformation>) {
+        self.device_information = info;
+    }
+}
+
+impl HeartRateSource for UnavailableSource {
+    fn state(&self) -> BoxFuture<Result<SourceState>> {
+        Box::pin(async { Ok(SourceState::Unknown) })
+    }
+
+    fn events(&self) -> BoxFuture<Result<EventStream>> {
+        let stream = self.events.stream();
+        Box::pin(async move { Ok(stream) })
+    }
+
+    fn start_scan(&self) -> BoxFuture<Result<()>> {
+        Box::pin(async { Ok(()) })
+    }
+
+    fn stop_scan(&self) -> BoxFuture<Result<()>> {
+        Box::pin(async { Ok(()) })
+    }
+}
+
+impl Deref for Device {
+    type Target = dyn HeartRateDevice;
+
+    fn deref(&self) -> &Self::Target {
+        self.inner.as_ref()
+    }
+}
+
+impl Display for DeviceId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl Display for Device {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.name() {
+            Some(name) => write!(f, "{name}"),
+            None => write!(f, "{}", self.id()),
+        }
+    }
+}
+
+impl Hash for Device {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        std::hash::Hash::hash(&self.id(), state)
+    }
+}
+
+impl PartialEq for Device {
+    fn eq(&self, other: &Self) -> bool {
+        self.id() == other.id()
+    }
+}
+
+impl Eq for Device {}
+
+impl From<u8> for BodySensorLocation {
+    fn from(value: u8) -> Self {
+        match value {
+            0 => Self::Other,
+            1 => Self::Chest,
+            2 => Self::Wrist,
+            3 => Self::Finger,
+            4 => Self::Hand,
+            5 => Self::EarLobe,
+            6 => Self::Foot,
+            v => Self::Reserved(v),
+        }
+    }
+}
+
+impl Display for DeviceInformation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let display_or_na = |v: &Option<String>| v.clone().unwrap_or_else(|| "N/A".into());
+        writeln!(
+            f,
+            "Manufacturer: {}",
+            display_or_na(&self.manufacturer_name)
+        )?;
+        writeln!(f, "Model: {}", display_or_na(&self.model_number))?;
+        writeln!(f, "Serial number: {}", display_or_na(&self.serial_number))?;
+        writeln!(f, "Firmware: {}", display_or_na(&self.firmware_revision))?;
+        writeln!(f, "Hardware: {}", display_or_na(&self.hardware_revision))
+    }
+}
@@ -0,0 +1,260 @@
+//! Summary of a connection
+
+use std::time::{Duration, Instant};
+
+use crate::hrm::HeartRateMeasurement;
+use crate::hrv::HrvMetrics;
+use crate::zones::HrZones;
+
+/// Accumulate statistics of a connection
+#[derive(Debug, Clone, Default)]
+pub struct SessionStats {
+    started: Option<Instant>,
+    last_update: Option<Instant>,
+    min_heart_rate: Option<u16>,
+    max_heart_rate: Option<u16>,
+    heart_rate_sum: u64,
+    heart_rate_count: u32,
+    time_in_zones: [Duration; HrZones::COUNT],
+    /// Energy expended accumulated before the last reset, unit: kJ
+    energy_before_reset: u32,
+    /// First and last energy expended since the last reset, unit: kJ
+    energy: Option<(u16, u16)>,
+    invalid_packets: u32,
+    hrv: HrvStats,
+}
+
+/// HRV metrics of all accepted RR-Intervals, accumulated without keeping the intervals
+#[derive(Debug, Clone, Default)]
+struct HrvStats {
+    count: u32,
+    /// Unit: ms
+    mean: f64,
+    /// Sum of squared deviations from `mean`
+    square_deviation_sum: f64,
+    /// The last interval, `None` after a gap
+    previous: Option<f32>,
+    /// Number of successive differences
+    diff_count: u32,
+    diff_square_sum: f64,
+    nn50: u32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SessionSummary {
+    pub duration: Duration,
+    pub min_heart_rate: Option<u16>,
+    pub avg_heart_rate: Option<u16>,
+    pub max_heart_rate: Option<u16>,
+    pub time_in_zones: [Duration; HrZones::COUNT],
+    /// Unit: kJ, `None` if the device doesn't report it
+    pub energy_expended: Option<u32>,
+    pub hrv: Option<HrvMetrics>,
+    pub invalid_packets: u32,
+}
+
+impl SessionStats {
+    pub fn push(&mut self, hrm: &HeartRateMeasurement, zones: &HrZones) {
+        let now = Instant::now();
+        let elapsed = self.last_update.map_or(Duration::ZERO, |v| now - v);
+        self.started.get_or_insert(now);
+        self.last_update = Some(now);
+
+        if let Some(energy) = hrm.energy_expended {
+            self.energy = match self.energy {
+                None => Some((energy, energy)),
+                // The energy expended was reset
+                Some((first, last)) if energy < last => {
+                    self.energy_before_reset += (last - first) as u32;
+                    Some((0, energy))
+                }
+                Some((first, _)) => Some((first, energy)),
+            };
+        }
+
+        // Without sensor contact the heart rate is meaningless
+        let hr = hrm.heart_rate;
+        if hr == 0 || hrm.contact_lost() {
+            return;
+        }
+        self.min_heart_rate = Some(self.min_heart_rate.map_or(hr, |v| v.min(hr)));
+        self.max_heart_rate = Some(self.max_heart_rate.map_or(hr, |v| v.max(hr)));
+        self.heart_rate_sum += hr as u64;
+        self.heart_rate_count += 1;
+        if let Some(zone) = zones.zone(hr) {
+            self.time_in_zones[zone.0] += elapsed;
+        }
+    }
+
+    pub fn push_invalid(&mut self) {
+        self.invalid_packets += 1;
+    }
+
+    /// Push an accepted RR-Interval, unit: ms
+    pub fn push_rr(&mut self, rr: f32) {
+        self.hrv.push(rr);
+    }
+
+    /// Mark a dropped RR-Interval, see [`Hrv::push_gap`](crate::hrv::Hrv::push_gap).
+    pub fn push_rr_gap(&mut self) {
+        self.hrv.previous = None;
+    }
+
+    /// `None` if nothing was received
+    pub fn summary(&self) -> Option<SessionSummary> {
+        if self.started.is_none() && self.invalid_packets == 0 {
+            return None;
+        }
+        let duration = match (self.started, self.last_update) {
+            (Some(start), Some(end)) => end - start,
+            _ => Duration::ZERO,
+        };
+        Some(SessionSummary {
+            duration,
+            min_heart_rate: self.min_heart_rate,
+            avg_heart_rate: (self.heart_rate_count > 0)
+                .then(|| (self.heart_rate_sum / self.heart_rate_count as u64) as u16),
+            max_heart_rate: self.max_heart_rate,
+            time_in_zones: self.time_in_zones,
+            energy_expended: self
+                .energy
+                .map(|(first, last)| self.energy_before_reset + (last - first) as u32),
+            hrv: self.hrv.metrics(),
+            invalid_packets: self.invalid_packets,
+        })
+    }
+}
+
+impl HrvStats {
+    fn push(&mut self, rr: f32) {
+        // Welford's online algorithm
+        self.count += 1;
+        let delta = rr as f64 - self.mean;
+        self.mean += delta / self.count as f64;
+        self.square_deviation_sum += delta * (rr as f64 - self.mean);
+
+        if let Some(previous) = self.previous {
+            let diff = (rr - previous) as f64;
+            self.diff_count += 1;
+            self.diff_square_sum += diff * diff;
+            self.nn50 += (diff.abs() > 50.0) as u32;
+        }
+        self.previous = Some(rr);
+    }
+
+    /// Same as [`Hrv::metrics`](crate::hrv::Hrv::metrics), but of the whole session.
+    fn metrics(&self) -> Option<HrvMetrics> {
+        if self.diff_count == 0 {
+            return None;
+        }
+        Some(HrvMetrics {
+            mean_nn: self.mean as f32,
+            sdnn: (self.square_deviation_sum / (self.count - 1) as f64).sqrt() as f32,
+            rmssd: (self.diff_square_sum / self.diff_count as f64).sqrt() as f32,
+            pnn50: (self.nn50 as f64 * 100.0 / self.diff_count as f64) as f32,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hrv::{Hrv, HrvWindow};
+
+    fn measurement(heart_rate: u8, energy: Option<u16>) -> HeartRateMeasurement {
+        let mut hrm = HeartRateMeasurement::parse(&[0, heart_rate]).unwrap();
+        hrm.energy_expended = energy;
+        hrm
+    }
+
+    fn assert_close(a: f32, b: f32) {
+        assert!((a - b).abs() < 1e-3, "{a} != {b}");
+    }
+
+    #[test]
+    fn nothing_received() {
+        assert!(SessionStats::default().summary().is_none());
+    }
+
+    #[test]
+    fn heart_rate_statistics() {
+        let mut stats = SessionStats::default();
+        let zones = HrZones::default();
+        [80, 0, 120, 101]
+            .into_iter()
+            .for_each(|hr| stats.push(&measurement(hr, None), &zones));
+        stats.push_invalid();
+
+        let summary = stats.summary().unwrap();
+        // The zero heart rate is ignored
+        assert_eq!(summary.min_heart_rate, Some(80));
+        assert_eq!(summary.avg_heart_rate, Some(100));
+        assert_eq!(summary.max_heart_rate, Some(120));
+        assert_eq!(summary.energy_expended, None);
+        assert_eq!(summary.hrv, None);
+        assert_eq!(summary.invalid_packets, 1);
+    }
+
+    #[test]
+    fn energy_expended_across_reset() {
+        let mut stats = SessionStats::default();
+        let zones = HrZones::default();
+        [Some(100), Some(150), None, Some(10), Some(30)]
+            .into_iter()
+            .for_each(|energy| stats.push(&measurement(80, energy), &zones));
+        assert_eq!(stats.summary().unwrap().energy_expended, Some(50 + 30));
+    }
+
+    #[test]
+    fn hrv_of_whole_session() {
+        let rr_intervals = (0..600)
+            .map(|i| 800.0 + (i % 7) as f32 * 20.0)
+            .collect::<Vec<_>>();
+        let mut stats = SessionStats::default();
+        stats.push_invalid();
+        rr_intervals.iter().for_each(|rr| stats.push_rr(*rr));
+        let mut window = Hrv::new(HrvWindow::Seconds30);
+        rr_intervals.iter().for_each(|rr| window.push(*rr));
+
+        let session = stats.summary().unwrap().hrv.unwrap();
+        let mean_nn = rr_intervals.iter().sum::<f32>() / 600.0;
+        let sdnn = (rr_intervals
+            .iter()
+            .map(|rr| (rr - mean_nn).powi(2))
+            .sum::<f32>()
+            / 599.0)
+            .sqrt();
+        assert_close(session.mean_nn, mean_nn);
+        assert_close(session.sdnn, sdnn);
+        // Differences are 20 ms six times, then -120 ms once
+        let rmssd = ((514.0 * 400.0 + 85.0 * 14400.0) / 599.0_f32).sqrt();
+        assert_close(session.rmssd, rmssd);
+        assert_close(session.pnn50, 85.0 * 100.0 / 599.0);
+        // The rolling window only covers the last 30 s
+        assert!(window.metrics().unwrap().mean_nn != session.mean_nn);
+    }
+
+    #[test]
+    fn hrv_differences_skip_gaps() {
+        let mut stats = SessionStats::default();
+        stats.push_invalid();
+        stats.push_rr(800.0);
+        stats.push_rr(900.0);
+        stats.push_rr_gap();
+        stats.push_rr(600.0);
+        stats.push_rr(620.0);
+
+        let hrv = stats.summary().unwrap().hrv.unwrap();
+        assert_close(hrv.mean_nn, 730.0);
+        assert_close(hrv.rmssd, ((100.0 * 100.0 + 20.0 * 20.0) / 2.0_f32).sqrt());
+        assert_close(hrv.pnn50, 50.0);
+
+        // A gap before any successive intervals leaves no differences
+        let mut stats = SessionStats::default();
+        stats.push_invalid();
+        stats.push_rr(800.0);
+        stats.push_rr_gap();
+        stats.push_rr(900.0);
+        assert_eq!(stats.summary().unwrap().hrv, None);
+    }
+}
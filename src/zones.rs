@@ -0,0 +1,137 @@
+use std::fmt::Display;
+
+use serde::{Deserialize, Serialize};
+
+/// How the zone boundaries are defined
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum ZoneMethod {
+    /// Percent of the maximum heart rate
+    #[default]
+    #[serde(rename = "percent_of_max")]
+    PercentOfMax,
+    /// Karvonen method, percent of the heart rate reserve (maximum - resting) above resting
+    #[serde(rename = "heart_rate_reserve")]
+    HeartRateReserve,
+    /// Boundaries in bpm
+    #[serde(rename = "custom")]
+    Custom,
+}
+
+/// Five heart rate zones, defined by their lower bounds. Zone `i` covers `bounds[i]..bounds[i + 1]`,
+/// the last zone has no upper bound, and heart rate below the first bound is in no zone.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HrZones {
+    pub method: ZoneMethod,
+    /// Unit: bpm
+    max_heart_rate: u16,
+    /// Unit: bpm
+    resting_heart_rate: u16,
+    /// Lower bounds in percent, used by `PercentOfMax` and `HeartRateReserve`
+    percents: [u16; HrZones::COUNT],
+    /// Lower bounds in bpm, used by `Custom`
+    custom: [u16; HrZones::COUNT],
+}
+
+/// Index of a zone, `0` for _Zone 1_
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Zone(pub usize);
+
+impl ZoneMethod {
+    pub const ALL: &[Self] = &[Self::PercentOfMax, Self::HeartRateReserve, Self::Custom];
+}
+
+impl HrZones {
+    pub const COUNT: usize = 5;
+
+    pub fn max_heart_rate(&self) -> u16 {
+        self.max_heart_rate
+    }
+
+    pub fn set_max_heart_rate(&mut self, value: u16) {
+        self.max_heart_rate = value.clamp(100, 240);
+        self.resting_heart_rate = self.resting_heart_rate.min(self.max_heart_rate - 1);
+    }
+
+    pub fn resting_heart_rate(&self) -> u16 {
+        self.resting_heart_rate
+    }
+
+    pub fn set_resting_heart_rate(&mut self, value: u16) {
+        self.resting_heart_rate = value.clamp(30, self.max_heart_rate - 1);
+    }
+
+    /// Lower bounds as edited with the current method, in percent or bpm
+    pub fn raw_bounds(&self) -> [u16; Self::COUNT] {
+        match self.method {
+            ZoneMethod::PercentOfMax | ZoneMethod::HeartRateReserve => self.percents,
+            ZoneMethod::Custom => self.custom,
+        }
+    }
+
+    /// Set a lower bound with the current method, it is kept between its neighbors.
+    pub fn set_raw_bound(&mut self, zone: Zone, value: u16) {
+        let bounds = match self.method {
+            ZoneMethod::PercentOfMax | ZoneMethod::HeartRateReserve => &mut self.percents,
+            ZoneMethod::Custom => &mut self.custom,
+        };
+        let Zone(i) = zone;
+        let min = if i == 0 {
+            1
+        } else {
+            bounds[i - 1].saturating_add(1)
+        };
+        let max = bounds.get(i + 1).map_or(u16::MAX, |v| v.saturating_sub(1));
+        if let Some(bound) = bounds.get_mut(i) {
+            *bound = value.clamp(min, max.max(min));
+        }
+    }
+
+    /// Lower bounds in bpm
+    pub fn bounds(&self) -> [u16; Self::COUNT] {
+        let (max, rest) = (self.max_heart_rate as u32, self.resting_heart_rate as u32);
+        match self.method {
+            ZoneMethod::PercentOfMax => self.percents.map(|v| (max * v as u32 / 100) as u16),
+            ZoneMethod::HeartRateReserve => self
+                .percents
+                .map(|v| (rest + max.saturating_sub(rest) * v as u32 / 100) as u16),
+            ZoneMethod::Custom => self.custom,
+        }
+    }
+
+    pub fn zone(&self, heart_rate: u16) -> Option<Zone> {
+        self.bounds()
+            .iter()
+            .rposition(|v| heart_rate >= *v)
+            .map(Zone)
+    }
+}
+
+impl Default for HrZones {
+    fn default() -> Self {
+        Self {
+            method: ZoneMethod::default(),
+            max_heart_rate: 190,
+            resting_heart_rate: 60,
+            percents: [50, 60, 70, 80, 90],
+            custom: [95, 114, 133, 152, 171],
+        }
+    }
+}
+
+impl Display for ZoneMethod {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let string = match self {
+            Self::PercentOfMax => "% HRmax",
+            Self::HeartRateReserve => "% HRR (Karvonen)",
+            Self::Custom => "bpm",
+        };
+        f.write_str(string)
+    }
+}
+
+impl Display for Zone {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Z{}", self.0 + 1)
+    }
+}